@@ -0,0 +1,61 @@
+//! Copies of the student's files with the grader's notes (e.g. `// GRADER:`
+//! comments) added, uploaded with the comment. The copies are kept in
+//! `<student>.annotated` so the submission itself is left alone.
+
+use dialoguer::{theme::ColorfulTheme, Confirm, MultiSelect};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Asks which files to annotate and opens a copy of each in the editor.
+/// Returns the copies that were changed.
+pub async fn offer(
+    dir: &Path,
+    files: &[PathBuf],
+    recording: Option<&crate::record::Recording>,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    if files.is_empty()
+        || !Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Annotate files to upload with the comment?")
+            .default(false)
+            .interact()?
+    {
+        return Ok(vec![]);
+    }
+
+    let names: Vec<_> = files
+        .iter()
+        .map(|f| f.file_name().unwrap_or_default().to_string_lossy())
+        .collect();
+
+    let selections = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Files to annotate")
+        .items(&names)
+        .interact()?;
+
+    let mut annotated = dir.to_path_buf().into_os_string();
+    annotated.push(".annotated");
+    let annotated = PathBuf::from(annotated);
+
+    fs::create_dir_all(&annotated).await?;
+
+    let mut changed = vec![];
+
+    for i in selections {
+        let original = fs::read(&files[i]).await?;
+        let copy = annotated.join(names[i].as_ref());
+
+        // keep notes from an earlier pass, e.g. after a restart
+        if !copy.exists() {
+            fs::write(&copy, &original).await?;
+        }
+
+        crate::open_in_editor(&copy, recording)?;
+
+        match fs::read(&copy).await? == original {
+            true => println!("{} unchanged, not uploading it", names[i]),
+            false => changed.push(copy),
+        }
+    }
+
+    Ok(changed)
+}
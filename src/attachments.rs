@@ -0,0 +1,316 @@
+//! Downloads every file a student attached to their submission, not just the
+//! first. Zip archives are extracted, anything else is saved as is.
+
+use crate::{canvas, extract, integrity, retry};
+use chrono::{DateTime, Local, Utc};
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, MultiSelect};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
+use tokio::fs;
+
+/// Conflicting paths listed before asking which archives to extract.
+const SHOWN_CONFLICTS: usize = 5;
+
+/// Attachments larger than this are flagged, most submissions are source
+/// code and a few kilobytes.
+const LARGE: u64 = 20 * 1024 * 1024;
+
+/// Archives `zip` can't extract, executables, and copies like
+/// `project (3).zip` or `lab - Copy.zip` that are often the wrong file.
+static SUSPICIOUS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\.(rar|7z|tar|gz|tgz|bz2|xz|exe|dmg|iso)$|\(\d+\)|\bcopy\b").unwrap()
+});
+
+/// Downloads of an attachment that arrives truncated or corrupt before
+/// giving up.
+const DOWNLOADS: usize = 3;
+
+struct Archive {
+    name: String,
+    bytes: Vec<u8>,
+    /// paths the archive extracts to
+    entries: Vec<String>,
+}
+
+/// File paths in the archive as extracted, without the top level directory
+/// when everything is inside one.
+fn entries(bytes: &[u8]) -> Option<Vec<String>> {
+    let archive = zip::ZipArchive::new(Cursor::new(bytes)).ok()?;
+    let names: Vec<_> = archive.file_names().filter(|n| !n.ends_with('/')).collect();
+
+    let top = names
+        .first()
+        .and_then(|n| n.split_once('/'))
+        .map(|(top, _)| format!("{top}/"))
+        .filter(|top| names.iter().all(|n| n.starts_with(top.as_str())));
+
+    Some(
+        names
+            .iter()
+            .map(|n| match &top {
+                Some(top) => n[top.len()..].to_string(),
+                None => n.to_string(),
+            })
+            .collect(),
+    )
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{b} B"),
+    }
+}
+
+/// The submission attachments come from, to get fresh urls for them when
+/// theirs expire during a long session.
+pub struct Source<'a> {
+    pub client: &'a canvas::Client,
+    pub course_id: usize,
+    pub assignment_id: usize,
+    pub user_id: usize,
+    /// the attempt downloaded, when not the latest
+    pub attempt: Option<usize>,
+}
+
+impl Source<'_> {
+    async fn fresh_url(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let (client, course_id, assignment_id, user_id) = (
+            self.client,
+            self.course_id,
+            self.assignment_id,
+            self.user_id,
+        );
+
+        let mut attachments = match self.attempt {
+            Some(attempt) => client
+                .get_submission_history(course_id, assignment_id, user_id)
+                .await?
+                .into_iter()
+                .find(|a| a.attempt == Some(attempt))
+                .map(|a| a.attachments)
+                .unwrap_or_default(),
+            None => {
+                client
+                    .get_submission_grade(course_id, assignment_id, user_id)
+                    .await?
+                    .attachments
+            }
+        };
+        if !attachments.iter().any(|a| a.display_name == name) {
+            attachments = client
+                .get_quiz_uploads(course_id, assignment_id, user_id, self.attempt)
+                .await?;
+        }
+
+        attachments
+            .into_iter()
+            .find(|a| a.display_name == name)
+            .map(|a| a.url)
+            .ok_or_else(|| format!("{name} is no longer attached to the submission").into())
+    }
+}
+
+/// Whether the download failed because the signed url expired.
+fn expired(e: &(dyn std::error::Error + 'static)) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .is_some_and(|s| s == StatusCode::UNAUTHORIZED || s == StatusCode::FORBIDDEN)
+}
+
+/// Downloads the attachment, checking it arrived whole: as long as the
+/// `Content-Length` canvas sent and, for a `.zip`, a readable archive. Each
+/// download follows the canvas url to a freshly signed one, and an expired
+/// url is replaced by one fetched from the submission again.
+async fn fetch(
+    name: &str,
+    url: &str,
+    source: &Source<'_>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut url = url.to_string();
+    let mut refreshed = false;
+
+    for download in 1.. {
+        let response = match retry::send(reqwest::Client::new().get(&url)).await {
+            Err(e) if !refreshed && expired(e.as_ref()) => {
+                println!(
+                    "{} link to {} expired, fetching a fresh one",
+                    "warning:".yellow(),
+                    name
+                );
+                url = source.fresh_url(name).await?;
+                refreshed = true;
+                continue;
+            }
+            response => response?,
+        };
+        let expected = response.content_length();
+        let bytes = response.bytes().await?.to_vec();
+
+        let problem = match expected {
+            Some(expected) if expected != bytes.len() as u64 => {
+                format!("arrived with {} of {} bytes", bytes.len(), expected)
+            }
+            _ if name.to_lowercase().ends_with(".zip") && entries(&bytes).is_none() => {
+                "is a corrupt zip".to_string()
+            }
+            _ => return Ok(bytes),
+        };
+
+        if download >= DOWNLOADS {
+            return Err(format!("{name} {problem} after {DOWNLOADS} downloads").into());
+        }
+
+        println!(
+            "{} {} {}, downloading again",
+            "warning:".yellow(),
+            name,
+            problem
+        );
+    }
+
+    unreachable!()
+}
+
+/// Lists each attachment's name, size and upload time, warning about ones
+/// that are unusually large or look like the wrong file.
+pub fn describe(attachments: &[canvas::Attachment]) {
+    for a in attachments {
+        let size = a.size.map(format_size).unwrap_or_else(|| "? B".into());
+        let uploaded = a
+            .created_at
+            .map(|at| {
+                at.with_timezone(&Local)
+                    .format("uploaded %a %b %e %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+
+        println!(
+            "\t{} {} {}",
+            a.display_name,
+            size.dimmed(),
+            uploaded.dimmed()
+        );
+
+        if a.size.is_some_and(|s| s > LARGE) {
+            println!("\t\t{} unusually large", "warning:".yellow());
+        }
+
+        if SUSPICIOUS.is_match(&a.display_name) {
+            println!("\t\t{} suspicious file name", "warning:".yellow());
+        }
+    }
+}
+
+/// Saves the attachments into `dir` as they are, e.g. files attached to
+/// submission comments.
+pub async fn save(
+    attachments: &[&canvas::Attachment],
+    dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir).await?;
+
+    for attachment in attachments {
+        let bytes = retry::send(reqwest::Client::new().get(&attachment.url))
+            .await?
+            .bytes()
+            .await?;
+
+        fs::write(dir.join(attachment.display_name.replace('/', "-")), bytes).await?;
+    }
+
+    Ok(())
+}
+
+/// Downloads `(file name, url)` attachments into `path`. When archives (or a
+/// plain attachment) would write the same files, the grader picks which
+/// archives to extract and the rest are saved unextracted. Returns integrity
+/// notes on the extracted archives.
+pub async fn download(
+    attachments: Vec<(String, String)>,
+    path: &Path,
+    submitted_at: Option<DateTime<Utc>>,
+    source: &Source<'_>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut archives = vec![];
+    let mut files = vec![];
+
+    for (name, url) in attachments {
+        let bytes = fetch(&name, &url, source).await?;
+        let name = name.replace('/', "-");
+
+        match entries(&bytes) {
+            Some(entries) => archives.push(Archive {
+                name,
+                bytes,
+                entries,
+            }),
+            None => files.push((name, bytes)),
+        }
+    }
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for entry in archives
+        .iter()
+        .flat_map(|a| &a.entries)
+        .chain(files.iter().map(|(name, _)| name))
+    {
+        *counts.entry(entry.as_str()).or_default() += 1;
+    }
+
+    let mut conflicts: Vec<_> = counts.into_iter().filter(|(_, n)| *n > 1).collect();
+    conflicts.sort();
+
+    let extracted = match conflicts.is_empty() {
+        true => vec![true; archives.len()],
+        false => {
+            println!("{} attachments overwrite each other:", "warning:".yellow());
+            for (entry, _) in conflicts.iter().take(SHOWN_CONFLICTS) {
+                println!("\t{}", entry);
+            }
+            if conflicts.len() > SHOWN_CONFLICTS {
+                println!("\t...and {} more", conflicts.len() - SHOWN_CONFLICTS);
+            }
+
+            let selections = MultiSelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Archives to extract, later ones overwrite earlier")
+                .items(&archives.iter().map(|a| &a.name).collect::<Vec<_>>())
+                .defaults(&vec![true; archives.len()])
+                .interact()?;
+
+            (0..archives.len())
+                .map(|i| selections.contains(&i))
+                .collect()
+        }
+    };
+
+    fs::create_dir_all(path).await?;
+
+    for (name, bytes) in &files {
+        fs::write(path.join(name), bytes).await?;
+    }
+
+    let mut notes = vec![];
+
+    for (archive, selected) in archives.into_iter().zip(extracted) {
+        if !selected {
+            fs::write(path.join(&archive.name), &archive.bytes).await?;
+            continue;
+        }
+
+        notes.extend(integrity::audit_timestamps(
+            Cursor::new(archive.bytes.clone()),
+            submitted_at,
+        )?);
+        extract(Cursor::new(archive.bytes), path).await?;
+    }
+
+    Ok(notes)
+}
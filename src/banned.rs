@@ -0,0 +1,220 @@
+//! Flags files using constructs the assignment bans, listed as
+//! `banned = ["system(", "goto", "strcpy"]` in `grader.toml`. Each is
+//! matched literally and, where it starts or ends with a letter, only as a
+//! whole word, so `goto` doesn't match `gotoNext`. Comments and string
+//! literals in C files are ignored.
+
+use crate::file_checks::{FileCheck, FileFinding, Item};
+use crate::{File, TEXT_ENTRY};
+use regex::Regex;
+
+pub struct Banned {
+    patterns: Vec<(String, Regex)>,
+    files: Regex,
+}
+
+fn pattern(banned: &str) -> Option<Regex> {
+    let word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+
+    let mut pattern = regex::escape(banned);
+    if word(banned.chars().next()) {
+        pattern.insert_str(0, r"\b");
+    }
+    if word(banned.chars().last()) {
+        pattern.push_str(r"\b");
+    }
+
+    Regex::new(&pattern).ok()
+}
+
+impl Banned {
+    /// `files` are the files of interest.
+    pub fn new(banned: &[String], files: Regex) -> Self {
+        Self {
+            patterns: banned
+                .iter()
+                .filter(|b| !b.trim().is_empty())
+                .filter_map(|b| Some((b.clone(), pattern(b.trim())?)))
+                .collect(),
+            files,
+        }
+    }
+
+    fn item(&self, file: &File) -> Item {
+        let contents = file.contents.as_deref().unwrap_or_default();
+        let name = file.name.to_lowercase();
+        let code = match name.ends_with(".c") || name.ends_with(".h") {
+            true => strip(contents),
+            false => contents.to_string(),
+        };
+
+        let found: Vec<_> = self
+            .patterns
+            .iter()
+            .filter_map(|(banned, re)| {
+                let lines: Vec<_> = code
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, l)| re.is_match(l))
+                    .map(|(i, _)| (i + 1).to_string())
+                    .collect();
+
+                match lines.len() {
+                    0 => None,
+                    1 => Some(format!("{} on line {}", banned, lines[0])),
+                    _ => Some(format!("{} on lines {}", banned, lines.join(", "))),
+                }
+            })
+            .collect();
+
+        Item {
+            name: match found.is_empty() {
+                true => file.name.clone(),
+                false => format!("{}: {}", file.name, found.join("; ")),
+            },
+            description: format!("{} doesn't use anything banned", file.name),
+            passed: found.is_empty(),
+        }
+    }
+}
+
+/// Blanks out C comments and string and character literals, keeping the
+/// line breaks so line numbers still match.
+pub fn strip(code: &str) -> String {
+    enum State {
+        Code,
+        LineComment,
+        BlockComment,
+        Literal(char),
+    }
+
+    let mut state = State::Code;
+    let mut out = String::with_capacity(code.len());
+    let mut chars = code.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let blank = match state {
+            State::Code => match (c, chars.peek()) {
+                ('/', Some('/')) => {
+                    state = State::LineComment;
+                    true
+                }
+                ('/', Some('*')) => {
+                    chars.next();
+                    out.push_str("  ");
+                    state = State::BlockComment;
+                    continue;
+                }
+                ('"' | '\'', _) => {
+                    state = State::Literal(c);
+                    false
+                }
+                _ => false,
+            },
+            State::LineComment => {
+                if c == '\n' {
+                    state = State::Code;
+                }
+                true
+            }
+            State::BlockComment => {
+                if c == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    out.push_str("  ");
+                    state = State::Code;
+                    continue;
+                }
+                true
+            }
+            State::Literal(quote) => {
+                if c == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        out.push(' ');
+                        out.push(if escaped == '\n' { '\n' } else { ' ' });
+                    }
+                    continue;
+                }
+                if c == quote || c == '\n' {
+                    state = State::Code;
+                    false
+                } else {
+                    true
+                }
+            }
+        };
+
+        match blank && c != '\n' {
+            true => out.push(' '),
+            false => out.push(c),
+        }
+    }
+
+    out
+}
+
+impl FileCheck for Banned {
+    fn run(&self, files: &[File]) -> FileFinding {
+        FileFinding {
+            title: "Banned constructs",
+            items: files
+                .iter()
+                .filter(|f| self.files.is_match(&f.name.to_lowercase()))
+                .filter(|f| !f.name.to_lowercase().contains("readme") && f.name != TEXT_ENTRY)
+                .map(|f| self.item(f))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_comments_keeping_lines() {
+        assert_eq!(strip("a // goto\nb"), format!("a{}\nb", " ".repeat(8)));
+
+        let stripped = strip("a /* goto\n goto */ b");
+        assert!(!stripped.contains("goto"));
+        assert_eq!(stripped.lines().count(), 2);
+        assert!(stripped.ends_with(" b"));
+    }
+
+    #[test]
+    fn strips_literals() {
+        assert_eq!(
+            strip(r#"puts("goto"); goto x;"#),
+            r#"puts("    "); goto x;"#
+        );
+        assert_eq!(strip("c = 'g';"), "c = ' ';");
+    }
+
+    #[test]
+    fn comment_markers_in_strings_are_not_comments() {
+        assert_eq!(
+            strip(r#"puts("// goto"); goto x;"#),
+            format!(r#"puts("{}"); goto x;"#, " ".repeat(7))
+        );
+        assert_eq!(strip(r#"s = "/*"; goto x;"#), r#"s = "  "; goto x;"#);
+    }
+
+    #[test]
+    fn escaped_quotes_stay_in_the_literal() {
+        assert_eq!(
+            strip(r#"s = "a\"goto"; x"#),
+            format!(r#"s = "{}"; x"#, " ".repeat(7))
+        );
+        assert_eq!(strip(r"c = '\''; goto x;"), r"c = '  '; goto x;");
+    }
+
+    #[test]
+    fn patterns_match_whole_words() {
+        let goto = pattern("goto").unwrap();
+        assert!(goto.is_match("goto end;"));
+        assert!(!goto.is_match("gotoNext();"));
+
+        let system = pattern("system(").unwrap();
+        assert!(system.is_match("system(\"ls\");"));
+        assert!(!system.is_match("filesystem(x);"));
+    }
+}
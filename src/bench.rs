@@ -0,0 +1,127 @@
+//! Timed checks for assignments graded partly on efficiency, e.g.
+//! `5 fast <= 2s: ./sort < big.txt`, or `5 fast <= 1.5x: ./sort < big.txt` to
+//! compare with the same command run in the assignment's `reference/`
+//! directory. The median of several runs is compared against the limit.
+//! Runs are timed with GNU time around the command itself, so a sandbox's
+//! startup doesn't count.
+
+use crate::{checks, sandbox};
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::sync::{OnceCell, RwLock};
+
+const RUNS: usize = 3;
+
+/// GNU time, for its `-o` and `-f`, unlike the shell builtin.
+pub const TIME: &str = "/usr/bin/time";
+
+/// Every other check holds this for reading and timed ones for writing, so
+/// timed runs have the machine to themselves: the batch workers pause while
+/// one runs.
+pub static TIMING: Lazy<RwLock<()>> = Lazy::new(|| RwLock::new(()));
+
+#[derive(Debug)]
+pub enum Limit {
+    Seconds(f64),
+    /// a multiple of the reference implementation's median, which is only
+    /// timed once
+    Reference {
+        factor: f64,
+        dir: PathBuf,
+        median: OnceCell<Option<f64>>,
+    },
+}
+
+/// Median wall time in seconds, or `None` if any run failed, along with the
+/// output of the last run.
+async fn median(
+    command: &str,
+    dir: &Path,
+    mounts: &[PathBuf],
+    backend: sandbox::Backend,
+) -> Result<(Option<f64>, String), Box<dyn std::error::Error>> {
+    let mut times = vec![];
+    let mut output = String::new();
+
+    let report = checks::report_path(dir, "elapsed");
+    let wrapped = format!(
+        "{} -o {} -f '%e' sh -c {}",
+        TIME,
+        sandbox::quote(&report.to_string_lossy()),
+        sandbox::quote(command)
+    );
+
+    for _ in 0..RUNS {
+        let (passed, run_output) = checks::execute(&wrapped, dir, mounts, backend).await?;
+        output = run_output;
+
+        // the last line, after e.g. `Command exited with non-zero status 1`
+        let elapsed = fs::read_to_string(&report)
+            .await
+            .ok()
+            .and_then(|r| r.lines().last()?.trim().parse::<f64>().ok());
+        let _ = fs::remove_file(&report).await;
+
+        if !passed {
+            return Ok((None, output));
+        }
+
+        let Some(elapsed) = elapsed else {
+            output.push_str("\nelapsed time unknown, is GNU time installed?");
+            return Ok((None, output));
+        };
+
+        times.push(elapsed);
+    }
+
+    times.sort_by(f64::total_cmp);
+
+    Ok((Some(times[RUNS / 2]), output))
+}
+
+pub async fn run(
+    command: &str,
+    limit: &Limit,
+    dir: &Path,
+    mounts: &[PathBuf],
+    backend: sandbox::Backend,
+) -> Result<(bool, String), Box<dyn std::error::Error>> {
+    let _timing = TIMING.write().await;
+
+    let (seconds, output) = median(command, dir, mounts, backend).await?;
+
+    let Some(seconds) = seconds else {
+        return Ok((false, output));
+    };
+
+    let allowed = match limit {
+        Limit::Seconds(seconds) => *seconds,
+        Limit::Reference {
+            factor,
+            dir: reference,
+            median: cached,
+        } => {
+            let reference = cached
+                .get_or_try_init(|| async {
+                    Ok::<_, Box<dyn std::error::Error>>(
+                        median(command, reference, mounts, backend).await?.0,
+                    )
+                })
+                .await?;
+
+            match reference {
+                Some(reference) => reference * factor,
+                None => return Ok((false, "the reference implementation failed".into())),
+            }
+        }
+    };
+
+    Ok((
+        seconds <= allowed,
+        format!(
+            "median {:.3}s over {} runs, limit {:.3}s",
+            seconds, RUNS, allowed
+        ),
+    ))
+}
@@ -0,0 +1,20 @@
+//! Opens pages in the default browser.
+
+use std::process::{Command, Stdio};
+
+/// Opens the url, printing it as well in case there is no browser, e.g. over
+/// ssh.
+pub fn open(url: &str) {
+    println!("Opening {url}");
+
+    let opener = match cfg!(target_os = "macos") {
+        true => "open",
+        false => "xdg-open",
+    };
+
+    let _ = Command::new(opener)
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
@@ -0,0 +1,81 @@
+//! Keeps the course, assignment and submission lists fetched from canvas in
+//! `.grader/cache/<canvas host>` for a while, so restarting after a crash doesn't fetch
+//! everything again. `--refresh` ignores what is cached.
+
+use serde_json::Value;
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::fs;
+
+const DIR: &str = ".grader/cache";
+
+/// How long lists are kept, unless `GRADER_CACHE_TTL` gives the minutes.
+const TTL: Duration = Duration::from_secs(15 * 60);
+
+fn ttl() -> Duration {
+    env::var("GRADER_CACHE_TTL")
+        .ok()
+        .and_then(|minutes| minutes.parse().ok())
+        .map(|minutes: u64| Duration::from_secs(minutes * 60))
+        .unwrap_or(TTL)
+}
+
+/// The cache file for an endpoint, e.g.
+/// `canvas.oregonstate.edu/courses-1-assignments.json`.
+fn path(base_url: &str, endpoint: &str, query: &[(&str, &str)]) -> PathBuf {
+    let host = base_url
+        .split("://")
+        .last()
+        .unwrap_or(base_url)
+        .trim_end_matches('/')
+        .replace('/', "-");
+
+    let name = std::iter::once(endpoint.to_string())
+        .chain(query.iter().map(|(k, v)| format!("{k}={v}")))
+        .collect::<Vec<_>>()
+        .join("-")
+        .replace(|c: char| !c.is_ascii_alphanumeric() && c != '=', "-");
+
+    PathBuf::from(DIR).join(host).join(format!("{name}.json"))
+}
+
+/// The cached list, unless it is older than the TTL.
+pub async fn load(base_url: &str, endpoint: &str, query: &[(&str, &str)]) -> Option<Vec<Value>> {
+    let path = path(base_url, endpoint, query);
+    let age = fs::metadata(&path)
+        .await
+        .ok()?
+        .modified()
+        .ok()?
+        .elapsed()
+        .ok()?;
+
+    if age > ttl() {
+        return None;
+    }
+
+    serde_json::from_slice(&fs::read(&path).await.ok()?).ok()
+}
+
+pub async fn store(
+    base_url: &str,
+    endpoint: &str,
+    query: &[(&str, &str)],
+    items: &[Value],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path(base_url, endpoint, query);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).await?;
+    }
+
+    fs::write(path, serde_json::to_vec(items)?).await?;
+
+    Ok(())
+}
+
+/// Drops the cached list, e.g. after changing something in it.
+pub async fn forget(base_url: &str, endpoint: &str, query: &[(&str, &str)]) {
+    let _ = fs::remove_file(path(base_url, endpoint, query)).await;
+}
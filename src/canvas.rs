@@ -0,0 +1,1026 @@
+//! Thin REST (and GraphQL) helpers for the parts of the Canvas API that the
+//! `canvasapi` crate does not cover.
+
+use crate::{cache, oauth, retry};
+use canvasapi::models::assignment::Assignment;
+use canvasapi::prelude::Submission;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, LINK, LOCATION};
+use reqwest::multipart::{Form, Part};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The url of the next page from a `Link` header like
+/// `<https://...&page=2>; rel="next", <https://...&page=1>; rel="first"`.
+fn next_page(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(LINK)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .find(|link| link.contains("rel=\"next\""))?
+        .split(';')
+        .next()
+        .map(|url| {
+            url.trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+}
+
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    tokens: oauth::Tokens,
+    /// lists are fetched even when cached, for `--refresh`
+    refresh: bool,
+}
+
+/// Whether canvas rejected the access token.
+fn unauthorized(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        == Some(StatusCode::UNAUTHORIZED)
+}
+
+impl Client {
+    pub fn new(base_url: &str, tokens: oauth::Tokens, refresh: bool) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.to_string(),
+            tokens,
+            refresh,
+        }
+    }
+
+    /// Sends the request with the access token, refreshing the token and
+    /// trying once more when canvas rejects it.
+    async fn send(&self, request: RequestBuilder) -> Result<Response, Box<dyn std::error::Error>> {
+        let token = self.tokens.get().await?;
+        let again = request.try_clone();
+
+        match retry::send(request.bearer_auth(&token)).await {
+            Err(e) if unauthorized(e.as_ref()) => match again {
+                Some(again) if self.tokens.refresh(&token).await? => {
+                    retry::send(again.bearer_auth(self.tokens.get().await?)).await
+                }
+                _ => Err(e),
+            },
+            result => result,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}api/v1/{}", self.base_url, path)
+    }
+
+    /// Runs a GraphQL query, failing on any error canvas reports.
+    pub async fn graphql<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        #[derive(Deserialize)]
+        struct Response<T> {
+            data: Option<T>,
+            #[serde(default)]
+            errors: Vec<GraphqlError>,
+        }
+
+        #[derive(Deserialize)]
+        struct GraphqlError {
+            message: String,
+        }
+
+        let response: Response<T> = self
+            .send(
+                self.http
+                    .post(format!("{}api/graphql", self.base_url))
+                    .json(&serde_json::json!({ "query": query, "variables": variables })),
+            )
+            .await?
+            .json()
+            .await?;
+
+        if let Some(error) = response.errors.first() {
+            return Err(format!("graphql: {}", error.message).into());
+        }
+
+        Ok(response.data.ok_or("graphql: no data")?)
+    }
+
+    /// SpeedGrader for the assignment, `&student_id=` picks the student.
+    pub fn speed_grader_url(&self, course_id: usize, assignment_id: usize) -> String {
+        format!(
+            "{}courses/{course_id}/gradebook/speed_grader?assignment_id={assignment_id}",
+            self.base_url
+        )
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        Ok(self
+            .send(
+                self.http
+                    .get(self.url(path))
+                    .query(&[("per_page", "100")])
+                    .query(query),
+            )
+            .await?
+            .json()
+            .await?)
+    }
+
+    /// Every page of a list endpoint, following the `Link` header canvas
+    /// paginates with.
+    async fn get_all<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Vec<T>, Box<dyn std::error::Error>> {
+        let mut items = vec![];
+
+        let mut response = self
+            .send(
+                self.http
+                    .get(self.url(path))
+                    .query(&[("per_page", "100")])
+                    .query(query),
+            )
+            .await?;
+
+        loop {
+            let next = next_page(response.headers());
+            items.extend(response.json::<Vec<T>>().await?);
+
+            let Some(next) = next else {
+                return Ok(items);
+            };
+
+            response = self.send(self.http.get(next)).await?;
+        }
+    }
+
+    /// Like `get_all`, but kept in the cache between runs.
+    async fn get_cached<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Vec<T>, Box<dyn std::error::Error>> {
+        let cached = match self.refresh {
+            true => None,
+            false => cache::load(&self.base_url, path, query).await,
+        };
+
+        let items = match cached {
+            Some(items) => items,
+            None => {
+                let items = self.get_all(path, query).await?;
+                cache::store(&self.base_url, path, query, &items).await?;
+                items
+            }
+        };
+
+        Ok(items
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()?)
+    }
+
+    pub async fn get_courses(&self) -> Result<Vec<Course>, Box<dyn std::error::Error>> {
+        self.get_cached("courses", &[("include[]", "term")]).await
+    }
+
+    /// Courses starred in canvas's course list.
+    pub async fn get_favorite_courses(&self) -> Result<Vec<Course>, Box<dyn std::error::Error>> {
+        self.get_all("users/self/favorites/courses", &[]).await
+    }
+
+    pub async fn get_assignments(
+        &self,
+        course_id: usize,
+    ) -> Result<Vec<Assignment>, Box<dyn std::error::Error>> {
+        self.get_cached(&format!("courses/{course_id}/assignments"), &[])
+            .await
+    }
+
+    pub async fn get_submissions(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+    ) -> Result<Vec<Submission>, Box<dyn std::error::Error>> {
+        self.get_cached(
+            &format!("courses/{course_id}/assignments/{assignment_id}/submissions"),
+            &[],
+        )
+        .await
+    }
+
+    /// The assignments along with the details `canvasapi` leaves out, like
+    /// how many submissions still need grading, read from the same list.
+    pub async fn get_assignments_with_summaries(
+        &self,
+        course_id: usize,
+    ) -> Result<(Vec<Assignment>, Vec<AssignmentSummary>), Box<dyn std::error::Error>> {
+        let items: Vec<serde_json::Value> = self
+            .get_cached(&format!("courses/{course_id}/assignments"), &[])
+            .await?;
+
+        let assignments = items
+            .iter()
+            .cloned()
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()?;
+        // only annotate the list, so one that doesn't parse is left out
+        let summaries = items
+            .into_iter()
+            .filter_map(|item| serde_json::from_value(item).ok())
+            .collect();
+
+        Ok((assignments, summaries))
+    }
+
+    pub async fn get_assignment_groups(
+        &self,
+        course_id: usize,
+    ) -> Result<Vec<AssignmentGroup>, Box<dyn std::error::Error>> {
+        self.get_all(&format!("courses/{course_id}/assignment_groups"), &[])
+            .await
+    }
+
+    pub async fn get_modules(
+        &self,
+        course_id: usize,
+    ) -> Result<Vec<Module>, Box<dyn std::error::Error>> {
+        self.get_all(
+            &format!("courses/{course_id}/modules"),
+            &[("include[]", "items")],
+        )
+        .await
+    }
+
+    pub async fn get_assignment_overrides(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+    ) -> Result<Vec<AssignmentOverride>, Box<dyn std::error::Error>> {
+        self.get_all(
+            &format!("courses/{course_id}/assignments/{assignment_id}/overrides"),
+            &[],
+        )
+        .await
+    }
+
+    pub async fn get_student_enrollments(
+        &self,
+        course_id: usize,
+    ) -> Result<Vec<Enrollment>, Box<dyn std::error::Error>> {
+        self.get_all(
+            &format!("courses/{course_id}/enrollments"),
+            &[("type[]", "StudentEnrollment")],
+        )
+        .await
+    }
+
+    /// Enrollments of the Test Student canvas adds for student view.
+    pub async fn get_test_student_enrollments(
+        &self,
+        course_id: usize,
+    ) -> Result<Vec<Enrollment>, Box<dyn std::error::Error>> {
+        self.get_all(
+            &format!("courses/{course_id}/enrollments"),
+            &[("type[]", "StudentViewEnrollment")],
+        )
+        .await
+    }
+
+    pub async fn get_sections(
+        &self,
+        course_id: usize,
+    ) -> Result<Vec<Section>, Box<dyn std::error::Error>> {
+        self.get_all(&format!("courses/{course_id}/sections"), &[])
+            .await
+    }
+
+    pub async fn get_course_students(
+        &self,
+        course_id: usize,
+    ) -> Result<Vec<User>, Box<dyn std::error::Error>> {
+        self.get_all(
+            &format!("courses/{course_id}/users"),
+            &[("enrollment_type[]", "student"), ("include[]", "email")],
+        )
+        .await
+    }
+
+    pub async fn get_submission_grade(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+    ) -> Result<SubmissionGrade, Box<dyn std::error::Error>> {
+        self.get(
+            &format!("courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}"),
+            &[],
+        )
+        .await
+    }
+
+    pub async fn get_submission_grades(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+    ) -> Result<Vec<SubmissionGrade>, Box<dyn std::error::Error>> {
+        self.get_all(
+            &format!("courses/{course_id}/assignments/{assignment_id}/submissions"),
+            &[],
+        )
+        .await
+    }
+
+    /// Grades with who gave them and the comments left on each submission.
+    pub async fn get_submission_feedback(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+    ) -> Result<Vec<SubmissionGrade>, Box<dyn std::error::Error>> {
+        self.get_all(
+            &format!("courses/{course_id}/assignments/{assignment_id}/submissions"),
+            &[
+                ("include[]", "submission_comments"),
+                ("include[]", "provisional_grades"),
+            ],
+        )
+        .await
+    }
+
+    /// Peer reviews assigned for the assignment, with the comments each
+    /// reviewer left.
+    pub async fn get_peer_reviews(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+    ) -> Result<Vec<PeerReview>, Box<dyn std::error::Error>> {
+        self.get_all(
+            &format!("courses/{course_id}/assignments/{assignment_id}/peer_reviews"),
+            &[("include[]", "submission_comments"), ("include[]", "user")],
+        )
+        .await
+    }
+
+    /// Teachers and TAs, who grade and comment.
+    pub async fn get_course_graders(
+        &self,
+        course_id: usize,
+    ) -> Result<Vec<User>, Box<dyn std::error::Error>> {
+        self.get_all(
+            &format!("courses/{course_id}/users"),
+            &[
+                ("enrollment_type[]", "teacher"),
+                ("enrollment_type[]", "ta"),
+            ],
+        )
+        .await
+    }
+
+    /// The group each student submitted with, for group assignments.
+    pub async fn get_submission_groups(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+    ) -> Result<Vec<SubmissionGroup>, Box<dyn std::error::Error>> {
+        self.get_all(
+            &format!("courses/{course_id}/assignments/{assignment_id}/submissions"),
+            &[("include[]", "group")],
+        )
+        .await
+    }
+
+    /// Every attempt the student submitted, oldest first.
+    pub async fn get_submission_history(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+    ) -> Result<Vec<Attempt>, Box<dyn std::error::Error>> {
+        #[derive(Deserialize)]
+        struct Response {
+            #[serde(default)]
+            submission_history: Vec<Attempt>,
+        }
+
+        let mut attempts = self
+            .get::<Response>(
+                &format!("courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}"),
+                &[("include[]", "submission_history")],
+            )
+            .await?
+            .submission_history;
+
+        attempts.retain(|a| a.submitted_at.is_some());
+        attempts.sort_by_key(|a| a.attempt);
+
+        Ok(attempts)
+    }
+
+    /// Files uploaded to a quiz's file upload questions, for the attempt or
+    /// the latest one.
+    pub async fn get_quiz_uploads(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+        attempt: Option<usize>,
+    ) -> Result<Vec<Attachment>, Box<dyn std::error::Error>> {
+        let attempts = self
+            .get_submission_history(course_id, assignment_id, user_id)
+            .await?;
+        let attempt = match attempt {
+            Some(n) => attempts.iter().find(|a| a.attempt == Some(n)),
+            None => attempts.last(),
+        };
+
+        let mut uploads = vec![];
+        for id in attempt.map(Attempt::quiz_uploads).unwrap_or_default() {
+            uploads.push(self.get::<Attachment>(&format!("files/{id}"), &[]).await?);
+        }
+
+        Ok(uploads)
+    }
+
+    pub async fn put_grade(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+        score: f64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.send(
+            self.http
+                .put(self.url(&format!(
+                    "courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}"
+                )))
+                .form(&[("submission[posted_grade]", score.to_string())]),
+        )
+        .await?;
+
+        // the cached list would still show the old grade after a restart
+        cache::forget(
+            &self.base_url,
+            &format!("courses/{course_id}/assignments/{assignment_id}/submissions"),
+            &[],
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Posts a submission comment, returning its id when canvas includes it.
+    pub async fn put_comment(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+        text: &str,
+        file_ids: &[usize],
+    ) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        #[derive(Deserialize)]
+        struct Response {
+            #[serde(default)]
+            submission_comments: Vec<SubmissionComment>,
+        }
+
+        let mut form = vec![("comment[text_comment]", text.to_string())];
+        form.extend(
+            file_ids
+                .iter()
+                .map(|id| ("comment[file_ids][]", id.to_string())),
+        );
+
+        Ok(self
+            .send(
+                self.http
+                    .put(self.url(&format!(
+                        "courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}"
+                    )))
+                    .form(&form),
+            )
+            .await?
+            .json::<Response>()
+            .await?
+            .submission_comments
+            .iter()
+            .map(|c| c.id)
+            .max())
+    }
+
+    /// Posts the score as this grader's provisional grade on a moderated
+    /// assignment, leaving the final grade to the moderator.
+    pub async fn put_provisional_grade(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+        score: f64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.send(
+            self.http
+                .put(self.url(&format!(
+                    "courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}"
+                )))
+                .form(&[
+                    ("submission[posted_grade]", score.to_string()),
+                    ("submission[provisional]", "true".to_string()),
+                ]),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Uploads a file to attach to a submission comment with `put_comment`,
+    /// returning its id. Canvas takes the file in three steps: ask for an
+    /// upload url, upload to it, then confirm the upload if redirected.
+    pub async fn upload_comment_file(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+        path: &Path,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        #[derive(Deserialize)]
+        struct Upload {
+            upload_url: String,
+            upload_params: HashMap<String, serde_json::Value>,
+        }
+
+        #[derive(Deserialize)]
+        struct Uploaded {
+            id: usize,
+        }
+
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let bytes = tokio::fs::read(path).await?;
+
+        let upload: Upload = self.send(
+            self.http
+                .post(self.url(&format!(
+                    "courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}/comments/files"
+                )))
+                .form(&[("name", name.clone()), ("size", bytes.len().to_string())]),
+        )
+        .await?
+        .json()
+        .await?;
+
+        // the upload url belongs to canvas's file store, which does not
+        // take the token and redirects to canvas to confirm
+        let uploader = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+
+        let (uploader, upload, name, bytes) = (&uploader, &upload, &name, &bytes);
+        let response = retry::retry(|| async move {
+            let mut form = Form::new();
+            for (key, value) in &upload.upload_params {
+                form = form.text(
+                    key.clone(),
+                    match value {
+                        serde_json::Value::String(value) => value.clone(),
+                        value => value.to_string(),
+                    },
+                );
+            }
+            form = form.part("file", Part::bytes(bytes.clone()).file_name(name.clone()));
+
+            Ok(uploader
+                .post(&upload.upload_url)
+                .multipart(form)
+                .send()
+                .await?
+                .error_for_status()?)
+        })
+        .await?;
+
+        let uploaded: Uploaded = match response.status().is_redirection() {
+            true => {
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .ok_or("upload redirected nowhere")?
+                    .to_str()?;
+
+                self.send(self.http.get(location)).await?.json().await?
+            }
+            false => response.json().await?,
+        };
+
+        Ok(uploaded.id)
+    }
+
+    /// Posts per criterion points and comments, e.g. from
+    /// `RubricAssessment::form`.
+    pub async fn put_rubric_assessment(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+        form: &[(String, String)],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.send(
+            self.http
+                .put(self.url(&format!(
+                    "courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}"
+                )))
+                .form(form),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Marks the submission read, clearing it from the grader's unread
+    /// indicators and to-do list.
+    pub async fn mark_read(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.send(self.http.put(self.url(&format!(
+            "courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}/read"
+        ))))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes the score, for reverting to a submission that was ungraded.
+    pub async fn clear_grade(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.send(
+            self.http
+                .put(self.url(&format!(
+                    "courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}"
+                )))
+                .form(&[("submission[posted_grade]", "")]),
+        )
+        .await?;
+
+        cache::forget(
+            &self.base_url,
+            &format!("courses/{course_id}/assignments/{assignment_id}/submissions"),
+            &[],
+        )
+        .await;
+
+        Ok(())
+    }
+
+    pub async fn delete_comment(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+        user_id: usize,
+        comment_id: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = format!(
+            "courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}/comments/{comment_id}"
+        );
+
+        self.send(self.http.delete(self.url(&path))).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_rubric(
+        &self,
+        course_id: usize,
+        assignment_id: usize,
+    ) -> Result<Vec<Criterion>, Box<dyn std::error::Error>> {
+        #[derive(Deserialize)]
+        struct Response {
+            rubric: Option<Vec<Criterion>>,
+        }
+
+        Ok(self
+            .get::<Response>(
+                &format!("courses/{course_id}/assignments/{assignment_id}"),
+                &[],
+            )
+            .await?
+            .rubric
+            .unwrap_or_default())
+    }
+
+    pub async fn get_late_policy(
+        &self,
+        course_id: usize,
+    ) -> Result<LatePolicy, Box<dyn std::error::Error>> {
+        #[derive(Deserialize)]
+        struct Response {
+            late_policy: LatePolicy,
+        }
+
+        Ok(self
+            .get::<Response>(&format!("courses/{course_id}/late_policy"), &[])
+            .await?
+            .late_policy)
+    }
+
+    pub async fn get_group_members(
+        &self,
+        group_id: usize,
+    ) -> Result<Vec<GroupMember>, Box<dyn std::error::Error>> {
+        self.get_all(&format!("groups/{group_id}/users"), &[]).await
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Course {
+    pub id: usize,
+    pub name: Option<String>,
+    pub term: Option<Term>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Term {
+    pub name: String,
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+}
+
+impl Term {
+    /// Terms without dates, like canvas's "Default Term", count as current.
+    pub fn is_current(&self) -> bool {
+        let now = Utc::now();
+
+        self.start_at.map_or(true, |start| start <= now)
+            && self.end_at.map_or(true, |end| now <= end)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignmentSummary {
+    pub id: usize,
+    pub due_at: Option<DateTime<Utc>>,
+    pub published: bool,
+    pub needs_grading_count: Option<usize>,
+    pub group_category_id: Option<usize>,
+    #[serde(default)]
+    pub grade_group_students_individually: bool,
+    #[serde(default)]
+    pub peer_reviews: bool,
+    pub assignment_group_id: Option<usize>,
+    #[serde(default)]
+    pub moderated_grading: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignmentGroup {
+    pub id: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Module {
+    pub name: String,
+    /// left out by canvas when the module has too many items
+    #[serde(default)]
+    pub items: Vec<ModuleItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModuleItem {
+    /// e.g. `Assignment`, `Quiz` or `Page`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub content_id: Option<usize>,
+}
+
+/// Keeps a field that is there but null apart from one that is missing.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignmentOverride {
+    pub student_ids: Option<Vec<usize>>,
+    pub group_id: Option<usize>,
+    pub course_section_id: Option<usize>,
+    /// missing when the override only changes when the assignment is locked,
+    /// null when it removes the due date
+    #[serde(default, deserialize_with = "present")]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Enrollment {
+    pub user_id: usize,
+    pub course_section_id: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Section {
+    pub id: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GroupMember {
+    pub id: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LatePolicy {
+    pub late_submission_deduction_enabled: bool,
+    /// percent deducted per interval
+    pub late_submission_deduction: f64,
+    /// `"day"` or `"hour"`
+    pub late_submission_interval: String,
+    pub late_submission_minimum_percent_enabled: bool,
+    pub late_submission_minimum_percent: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub id: usize,
+    pub name: String,
+    pub sortable_name: String,
+    pub login_id: Option<String>,
+    pub email: Option<String>,
+    /// only shown to those allowed to see it
+    pub sis_user_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Criterion {
+    pub id: String,
+    pub description: String,
+    pub points: f64,
+    pub ratings: Vec<Rating>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Rating {
+    pub id: String,
+    pub description: String,
+    pub points: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Attempt {
+    pub attempt: Option<usize>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    pub url: Option<String>,
+    pub body: Option<String>,
+    /// the answers, on quiz submissions
+    pub submission_data: Option<Vec<QuizAnswer>>,
+}
+
+impl Attempt {
+    /// Ids of the files uploaded to file upload questions.
+    pub fn quiz_uploads(&self) -> Vec<&str> {
+        self.submission_data
+            .iter()
+            .flatten()
+            .flat_map(|a| a.attachment_ids.iter().flatten())
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuizAnswer {
+    pub question_id: Option<usize>,
+    /// only on file upload questions, null when left unanswered
+    pub attachment_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Attachment {
+    pub display_name: String,
+    pub url: String,
+    /// bytes
+    pub size: Option<u64>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubmissionComment {
+    pub id: usize,
+    pub author_name: Option<String>,
+    pub comment: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PeerReview {
+    /// the student reviewed
+    pub user_id: usize,
+    pub assessor: Option<Assessor>,
+    /// `assigned` until the review is done, then `completed`
+    pub workflow_state: String,
+    #[serde(default)]
+    pub submission_comments: Vec<SubmissionComment>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Assessor {
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmissionGroup {
+    pub user_id: usize,
+    pub group: Option<Group>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Group {
+    pub id: Option<usize>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmissionGrade {
+    pub user_id: usize,
+    pub score: Option<f64>,
+    /// negative for canvas's autograders
+    pub grader_id: Option<i64>,
+    pub graded_at: Option<DateTime<Utc>>,
+    /// of the latest attempt
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub submission_comments: Vec<SubmissionComment>,
+    /// every grader's mark on a moderated assignment, for moderators
+    #[serde(default)]
+    pub provisional_grades: Vec<ProvisionalGrade>,
+    /// null rather than false for most submissions
+    pub excused: Option<bool>,
+}
+
+impl SubmissionGrade {
+    pub fn is_excused(&self) -> bool {
+        self.excused.unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProvisionalGrade {
+    pub score: Option<f64>,
+    pub scorer_id: Option<usize>,
+    /// picked by the moderator as the final grade
+    #[serde(default, rename = "final")]
+    pub is_final: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn override_due_dates() {
+        let lock_only: AssignmentOverride =
+            serde_json::from_str(r#"{"student_ids": [1], "lock_at": "2024-10-08T06:59:00Z"}"#)
+                .unwrap();
+        assert_eq!(lock_only.due_at, None);
+
+        let removed: AssignmentOverride =
+            serde_json::from_str(r#"{"student_ids": [1], "due_at": null}"#).unwrap();
+        assert_eq!(removed.due_at, Some(None));
+
+        let extended: AssignmentOverride =
+            serde_json::from_str(r#"{"student_ids": [1], "due_at": "2024-10-08T06:59:00Z"}"#)
+                .unwrap();
+        assert_eq!(
+            extended.due_at,
+            Some(Some("2024-10-08T06:59:00Z".parse().unwrap()))
+        );
+    }
+}
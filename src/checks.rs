@@ -0,0 +1,592 @@
+//! Commands run in each submission before grading.
+//!
+//! The assignment's `checks.txt` has one check per line, e.g.
+//! `10 compiles: make` awards 10 points when `make` exits successfully in the
+//! student's directory. Timed and memory limited checks are described in
+//! [`crate::bench`] and [`crate::memory`].
+//!
+//! Tests from the assignment's `tests/` directory run after them, see
+//! [`crate::harness`], followed by their memory checks, see
+//! [`crate::memcheck`].
+//!
+//! `build.txt` overrides how the submission is built, e.g. `make -f grader.mk`
+//! with a wrapper Makefile from `inject/` that includes the student's. It runs
+//! first as a check worth no points. With `--build`, assignments without one
+//! are built with `make`. The build's errors and warnings are counted from the
+//! compiler's output.
+
+use crate::{
+    bench, config, crash, harness, inject, memcheck, memory, sandbox, DownloadedSubmission,
+};
+use colored::Colorize;
+use futures::future;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+use tokio::process::{Child, Command};
+use walkdir::WalkDir;
+
+/// Checks still running after this long fail, unless `GRADER_TIMEOUT` gives
+/// the seconds.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Name of the check running `build.txt`.
+pub const BUILD: &str = "build";
+
+/// Build command used when the assignment has no `build.txt` and `--build` is
+/// given.
+const DEFAULT_BUILD: &str = "make";
+
+/// Where batch workers copy submissions to build them.
+const SCRATCH: &str = ".grader/scratch";
+
+/// Default bytes of output kept per command.
+const OUTPUT_LIMIT: usize = 64 * 1024;
+
+/// Default bytes of output after which a command is killed.
+const OUTPUT_KILL: usize = 16 * 1024 * 1024;
+
+/// Lines of output shown for a failed check.
+const OUTPUT_LINES: usize = 5;
+
+static LINE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d+(?:\.\d+)?)\s+([^:]+):\s*(.+)$").unwrap());
+
+/// `file.c:3:5: error: ...` from gcc and clang, `error`/`warning` also
+/// matching make's and the linker's messages.
+static DIAGNOSTIC: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b(error|warning)\b:").unwrap());
+
+/// `5 fast <= 2s: command`, `5 fast <= 1.5x: command` or
+/// `5 small <= 64M: command`
+static LIMITED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(\d+(?:\.\d+)?)\s+([^:]+?)\s*<=\s*(\d+(?:\.\d+)?)(s|x|K|M|G)\s*:\s*(.+)$")
+        .unwrap()
+});
+
+#[derive(Debug)]
+pub enum Kind {
+    /// passes when the command succeeds
+    Pass,
+    /// passes when the command succeeds within the time limit
+    Time(bench::Limit),
+    /// passes when the command succeeds within this many KiB
+    Memory(u64),
+    /// passes when the tool finds no memory errors or leaks
+    Memcheck(memcheck::Tool),
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub points: f64,
+    pub name: String,
+    pub command: String,
+    pub kind: Kind,
+    /// the assignment's directories the command reads, e.g. `tests/`,
+    /// readable in the sandbox
+    pub mounts: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: String,
+    pub points: f64,
+    pub passed: bool,
+    pub output: String,
+}
+
+impl CheckResult {
+    pub fn score(&self) -> f64 {
+        match self.passed {
+            true => self.points,
+            false => 0.0,
+        }
+    }
+}
+
+fn parse_line(line: &str, assignment_dir: Option<&Path>) -> Option<Check> {
+    if let Some(caps) = LIMITED.captures(line) {
+        let value: f64 = caps[3].parse().ok()?;
+
+        let kind = match &caps[4] {
+            "s" => Kind::Time(bench::Limit::Seconds(value)),
+            "x" => Kind::Time(bench::Limit::Reference {
+                factor: value,
+                dir: assignment_dir?.join(config::REFERENCE),
+                median: Default::default(),
+            }),
+            "K" => Kind::Memory(value as u64),
+            "M" => Kind::Memory((value * 1024.0) as u64),
+            _ => Kind::Memory((value * 1024.0 * 1024.0) as u64),
+        };
+
+        return Some(Check {
+            points: caps[1].parse().ok()?,
+            name: caps[2].trim().to_string(),
+            command: caps[5].trim().to_string(),
+            kind,
+            mounts: vec![],
+        });
+    }
+
+    let caps = LINE.captures(line)?;
+
+    Some(Check {
+        points: caps[1].parse().ok()?,
+        name: caps[2].trim().to_string(),
+        command: caps[3].trim().to_string(),
+        kind: Kind::Pass,
+        mounts: vec![],
+    })
+}
+
+pub fn parse(contents: &str, assignment_dir: Option<&Path>) -> Vec<Check> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| parse_line(l, assignment_dir))
+        .collect()
+}
+
+/// The build command, if any, followed by the checks, only those
+/// `grader.toml` lists when it lists any. With `build_by_default`, builds with `make` when the assignment has no
+/// `build.txt`.
+pub async fn load(assignment_dir: Option<&Path>, build_by_default: bool) -> Vec<Check> {
+    let settings = config::settings(assignment_dir).await;
+
+    let build = config::read(assignment_dir, config::BUILD)
+        .await
+        .map(|b| b.trim().to_string())
+        .filter(|b| !b.is_empty())
+        .or_else(|| build_by_default.then(|| DEFAULT_BUILD.to_string()))
+        .map(|command| Check {
+            points: 0.0,
+            name: BUILD.into(),
+            command,
+            kind: Kind::Pass,
+            mounts: vec![],
+        });
+
+    build
+        .into_iter()
+        .chain(
+            config::read(assignment_dir, config::CHECKS)
+                .await
+                .map(|c| parse(&c, assignment_dir))
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|c| {
+                    settings
+                        .checks
+                        .as_ref()
+                        .map_or(true, |names| names.contains(&c.name))
+                }),
+        )
+        .chain(harness::load(assignment_dir, &settings))
+        .chain(memcheck::load(assignment_dir, &settings))
+        .collect()
+}
+
+pub fn timeout() -> Duration {
+    env::var("GRADER_TIMEOUT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(TIMEOUT)
+}
+
+/// The virtual memory each command may use from `GRADER_MEMORY_LIMIT`, in
+/// KiB, e.g. `512M`. Unlimited when not set, since sanitizer builds reserve
+/// far more than they use.
+fn memory_limit() -> Option<u64> {
+    let limit = env::var("GRADER_MEMORY_LIMIT").ok()?;
+    let limit = limit.trim();
+
+    let (value, unit) = match limit.char_indices().last()? {
+        (i, 'K' | 'k') => (&limit[..i], 1),
+        (i, 'M' | 'm') => (&limit[..i], 1024),
+        (i, 'G' | 'g') => (&limit[..i], 1024 * 1024),
+        _ => (limit, 1),
+    };
+
+    value.trim().parse::<u64>().ok().map(|v| v * unit)
+}
+
+/// Kills the command's whole process group, so programs it started don't
+/// outlive it.
+fn kill_group(pid: Option<u32>) {
+    if let Some(pid) = pid {
+        let _ = std::process::Command::new("kill")
+            .args(["-KILL", "--", &format!("-{pid}")])
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+fn env_bytes(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Reads stdout and stderr as they are written, keeping the first `keep`
+/// bytes. Returns the kept output, the total size and whether the child was
+/// killed for printing more than `kill` bytes.
+async fn capture(
+    child: &mut Child,
+    keep: usize,
+    kill: usize,
+) -> Result<(Vec<u8>, usize, bool), Box<dyn std::error::Error>> {
+    let mut stdout = child.stdout.take().ok_or("stdout not piped")?;
+    let mut stderr = child.stderr.take().ok_or("stderr not piped")?;
+
+    let (mut out_buf, mut err_buf) = ([0; 8192], [0; 8192]);
+    let (mut out_open, mut err_open) = (true, true);
+
+    let mut kept = vec![];
+    let mut total = 0;
+
+    while out_open || err_open {
+        let (n, from_stdout) = tokio::select! {
+            n = stdout.read(&mut out_buf), if out_open => (n?, true),
+            n = stderr.read(&mut err_buf), if err_open => (n?, false),
+        };
+
+        let chunk = match from_stdout {
+            true => {
+                out_open = n > 0;
+                &out_buf[..n]
+            }
+            false => {
+                err_open = n > 0;
+                &err_buf[..n]
+            }
+        };
+
+        total += chunk.len();
+        kept.extend_from_slice(&chunk[..chunk.len().min(keep.saturating_sub(kept.len()))]);
+
+        if total > kill {
+            kill_group(child.id());
+            child.kill().await?;
+            return Ok((kept, total, true));
+        }
+    }
+
+    Ok((kept, total, false))
+}
+
+/// Runs a command in `dir`, returning whether it succeeded and its output.
+///
+/// Only the first `GRADER_OUTPUT_LIMIT` bytes (default 64 KiB) of output are
+/// kept, and commands printing more than `GRADER_OUTPUT_KILL` bytes (default
+/// 16 MiB) are killed, so a runaway print loop cannot stall a batch. Commands
+/// running longer than the timeout are killed along with everything they
+/// started, and `GRADER_MEMORY_LIMIT` caps their memory. They run in the
+/// `backend`'s [`sandbox`], where only `dir` and `mounts` are there.
+pub async fn execute(
+    command: &str,
+    dir: &Path,
+    mounts: &[PathBuf],
+    backend: sandbox::Backend,
+) -> Result<(bool, String), Box<dyn std::error::Error>> {
+    let keep = env_bytes("GRADER_OUTPUT_LIMIT", OUTPUT_LIMIT);
+    let kill = env_bytes("GRADER_OUTPUT_KILL", OUTPUT_KILL);
+
+    let limited = match memory_limit() {
+        Some(kib) => format!("ulimit -v {kib}; {command}"),
+        None => command.to_string(),
+    };
+
+    let mut sandboxed = sandbox::command(&limited, dir, mounts, backend);
+    let mut child = sandboxed
+        .command
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let pid = child.id();
+
+    // the child is killed on drop if this times out
+    let run = async move {
+        let (kept, total, killed) = capture(&mut child, keep, kill).await?;
+        let status = child.wait().await?;
+
+        Ok::<_, Box<dyn std::error::Error>>((status, kept, total, killed))
+    };
+
+    let timeout = timeout();
+
+    let Ok(result) = tokio::time::timeout(timeout, run).await else {
+        kill_group(pid);
+        sandboxed.stop();
+        return Ok((false, format!("timed out after {}s", timeout.as_secs())));
+    };
+
+    let (status, kept, total, killed) = result?;
+    if killed {
+        sandboxed.stop();
+    }
+    let mut text = String::from_utf8_lossy(&kept).into_owned();
+
+    if killed {
+        text.push_str(&format!("\n[killed after printing more than {kill} bytes]"));
+    } else if total > kept.len() {
+        text.push_str(&format!(
+            "\n[output truncated, {} of {} bytes shown]",
+            kept.len(),
+            total
+        ));
+    }
+
+    if let Some(signal) = crash::signal(status) {
+        text.push_str(&format!(
+            "\n[crashed with {signal}]\n{}",
+            crash::backtrace(command, dir, mounts, backend).await
+        ));
+    }
+
+    Ok((status.success() && !killed, text))
+}
+
+/// Where a tool wrapping a command writes its report, apart from the output
+/// so truncating that loses nothing. It's in `dir`, which the sandbox can
+/// write to at the same path.
+pub fn report_path(dir: &Path, tool: &str) -> PathBuf {
+    sandbox::absolute(dir).join(format!(".grader-{tool}"))
+}
+
+pub async fn run(
+    check: &Check,
+    dir: &Path,
+    backend: sandbox::Backend,
+) -> Result<CheckResult, Box<dyn std::error::Error>> {
+    // timed checks take the lock for writing themselves
+    let _timing = match check.kind {
+        Kind::Time(_) => None,
+        _ => Some(bench::TIMING.read().await),
+    };
+
+    let (passed, output) = match &check.kind {
+        Kind::Pass => execute(&check.command, dir, &check.mounts, backend).await?,
+        Kind::Time(limit) => bench::run(&check.command, limit, dir, &check.mounts, backend).await?,
+        Kind::Memory(limit) => {
+            memory::run(&check.command, *limit, dir, &check.mounts, backend).await?
+        }
+        Kind::Memcheck(tool) => {
+            memcheck::run(&check.command, *tool, dir, &check.mounts, backend).await?
+        }
+    };
+
+    Ok(CheckResult {
+        name: check.name.clone(),
+        points: check.points,
+        passed,
+        output,
+    })
+}
+
+pub async fn run_all(
+    checks: &[Check],
+    dir: &Path,
+    backend: sandbox::Backend,
+) -> Result<Vec<CheckResult>, Box<dyn std::error::Error>> {
+    let mut results = vec![];
+
+    for check in checks {
+        results.push(run(check, dir, backend).await?);
+    }
+
+    Ok(results)
+}
+
+/// Copies a submission into a scratch directory, leaving out `.git` and
+/// recreating symlinks rather than copying what they point to.
+async fn copy_dir(from: &Path, to: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    for entry in WalkDir::new(from)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+    {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+
+        // links stay links, so one to e.g. `~/.ssh/id_rsa` doesn't put the
+        // grader's file where the sandbox can read it
+        if entry.file_type().is_symlink() {
+            fs::symlink(fs::read_link(entry.path()).await?, &target).await?;
+        } else if entry.file_type().is_dir() {
+            fs::create_dir_all(&target).await?;
+        } else {
+            fs::copy(entry.path(), &target).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn worker(
+    id: usize,
+    queue: &Mutex<Vec<&DownloadedSubmission>>,
+    results: &Mutex<HashMap<usize, Vec<CheckResult>>>,
+    checks: &[Check],
+    assignment_dir: Option<&Path>,
+    backend: sandbox::Backend,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let scratch = Path::new(SCRATCH).join(id.to_string());
+
+    loop {
+        let Some(s) = queue.lock().unwrap().pop() else {
+            break;
+        };
+
+        if scratch.exists() {
+            fs::remove_dir_all(&scratch).await?;
+        }
+
+        copy_dir(&s.path, &scratch).await?;
+        inject::inject(assignment_dir, &scratch).await?;
+
+        let checked = run_all(checks, &scratch, backend).await?;
+        let marker = match checked.iter().all(|r| r.passed) {
+            true => "✔".green(),
+            false => "✗".red(),
+        };
+
+        println!(
+            "\t{} {} ({}/{})",
+            marker,
+            s.name,
+            checked.iter().map(CheckResult::score).sum::<f64>(),
+            checked.iter().map(|r| r.points).sum::<f64>()
+        );
+
+        results.lock().unwrap().insert(s.user_id, checked);
+    }
+
+    if scratch.exists() {
+        fs::remove_dir_all(&scratch).await?;
+    }
+
+    Ok(())
+}
+
+/// Runs every submission's checks up front with one worker per core. Each
+/// worker builds in its own scratch copy so builds cannot collide and the
+/// submissions themselves are left untouched.
+pub async fn run_batch(
+    checks: &[Check],
+    assignment_dir: Option<&Path>,
+    submissions: &[DownloadedSubmission],
+    backend: sandbox::Backend,
+) -> Result<HashMap<usize, Vec<CheckResult>>, Box<dyn std::error::Error>> {
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(submissions.len());
+
+    println!(
+        "Running checks on {} submissions with {} workers...",
+        submissions.len(),
+        workers
+    );
+
+    let queue = Mutex::new(submissions.iter().collect::<Vec<_>>());
+    let results = Mutex::new(HashMap::new());
+
+    future::try_join_all(
+        (0..workers).map(|id| worker(id, &queue, &results, checks, assignment_dir, backend)),
+    )
+    .await?;
+
+    Ok(results.into_inner().unwrap())
+}
+
+/// Counts the build's errors and warnings, showing the first few of them even
+/// when it succeeded. Returns whether there were any to show.
+fn print_diagnostics(output: &str) -> bool {
+    let diagnostics: Vec<_> = output
+        .lines()
+        .filter_map(|l| Some((DIAGNOSTIC.captures(l)?[1].to_lowercase(), l)))
+        .collect();
+
+    let errors = diagnostics
+        .iter()
+        .filter(|(kind, _)| kind == "error")
+        .count();
+    let warnings = diagnostics.len() - errors;
+
+    let count = |n: usize, what: &str| {
+        let text = format!("{n} {what}{}", if n == 1 { "" } else { "s" });
+
+        match (n, what) {
+            (0, _) => text.green(),
+            (_, "error") => text.red(),
+            _ => text.yellow(),
+        }
+    };
+
+    println!(
+        "\t\t{}, {}",
+        count(errors, "error"),
+        count(warnings, "warning")
+    );
+
+    for (_, line) in diagnostics.iter().take(OUTPUT_LINES) {
+        println!("\t\t{}", line.dimmed());
+    }
+
+    !diagnostics.is_empty()
+}
+
+pub fn print(results: &[CheckResult]) {
+    println!("Checks:");
+
+    for r in results {
+        let marker = match r.passed {
+            true => "✔".green(),
+            false => "✗".red(),
+        };
+
+        println!("\t{} {} ({}/{})", marker, r.name, r.score(), r.points);
+
+        let shown = r.name == BUILD && print_diagnostics(&r.output);
+
+        if !r.passed && !shown {
+            let lines: Vec<_> = r.output.trim_end().lines().collect();
+
+            for line in &lines[lines.len().saturating_sub(OUTPUT_LINES)..] {
+                println!("\t\t{}", line.dimmed());
+            }
+        }
+    }
+
+    let tests: Vec<_> = results
+        .iter()
+        .filter(|r| r.name.starts_with(harness::PREFIX))
+        .collect();
+
+    if !tests.is_empty() {
+        let passed = tests.iter().filter(|r| r.passed).count();
+        let summary = format!(
+            "Tests: {}/{} passed, {}/{} points",
+            passed,
+            tests.len(),
+            tests.iter().map(|r| r.score()).sum::<f64>(),
+            tests.iter().map(|r| r.points).sum::<f64>()
+        );
+
+        match passed == tests.len() {
+            true => println!("{}", summary.green()),
+            false => println!("{}", summary.yellow()),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Claims on students, so graders working through the same assignment at
+//! once don't grade anyone twice. With `--claims <dir>` on a share every
+//! grader can write to, a student is claimed with a file
+//! `<dir>/<assignment id>/<user id>.claim` just before being graded, and
+//! students claimed by someone else are left out of the list to grade.
+//!
+//! Graders are told apart by `GRADER_NAME` (default `$USER`), and their own
+//! claims never hide a student from them. Claims stay once a grade is posted
+//! and are released when the student is skipped or put off, or when the
+//! grader exits. Deleting the file frees a claim left by a crash.
+
+use chrono::Local;
+use colored::Colorize;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// The claim on the student being graded, released when dropped unless kept.
+#[derive(Debug)]
+pub struct Claim {
+    path: Option<PathBuf>,
+}
+
+impl Claim {
+    /// Keeps the claim of the student just graded.
+    pub fn keep(mut self) {
+        self.path = None;
+    }
+
+    /// Releases the claim, for a student skipped or put off.
+    pub fn release(self) {}
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn me() -> String {
+    env::var("GRADER_NAME")
+        .or_else(|_| env::var("USER"))
+        .unwrap_or_else(|_| "unknown".into())
+}
+
+/// The student's claim in `dir`, the `--claims` directory if one was given.
+fn path(dir: Option<&Path>, assignment_id: usize, user_id: usize) -> Option<PathBuf> {
+    Some(
+        dir?.join(assignment_id.to_string())
+            .join(format!("{user_id}.claim")),
+    )
+}
+
+/// Who claimed the student, the first line of the claim.
+fn owner(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()?
+        .lines()
+        .next()
+        .map(str::to_string)
+}
+
+/// The grader who claimed the student, unless it's nobody or this grader.
+pub fn claimed_by_other(
+    dir: Option<&Path>,
+    assignment_id: usize,
+    user_id: usize,
+) -> Option<String> {
+    owner(&path(dir, assignment_id, user_id)?).filter(|o| *o != me())
+}
+
+/// Claims the student before grading them. Fails with who has them when
+/// it's another grader. Claims that can't be written are warned about and
+/// grading goes on, so a flaky share doesn't stop anyone.
+pub fn claim(dir: Option<&Path>, assignment_id: usize, user_id: usize) -> Result<Claim, String> {
+    let Some(path) = path(dir, assignment_id, user_id) else {
+        return Ok(Claim { path: None });
+    };
+
+    let created = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| OpenOptions::new().write(true).create_new(true).open(&path));
+
+    match created {
+        Ok(mut file) => {
+            if let Err(e) = writeln!(file, "{}\n{}", me(), Local::now().to_rfc3339()) {
+                println!("{} couldn't write the claim: {}", "warning:".yellow(), e);
+            }
+        }
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            if let Some(owner) = owner(&path).filter(|o| *o != me()) {
+                return Err(owner);
+            }
+        }
+        Err(e) => {
+            println!("{} couldn't claim the student: {}", "warning:".yellow(), e);
+            return Ok(Claim { path: None });
+        }
+    }
+
+    Ok(Claim { path: Some(path) })
+}
@@ -0,0 +1,152 @@
+//! Per-assignment configuration lives in a directory named after the
+//! assignment's id or name under `GRADER_ASSIGNMENTS` (default
+//! `assignments/`).
+
+use crate::{memcheck, style};
+use colored::Colorize;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Settings for assignments the defaults don't fit, see [`Settings`].
+pub const SETTINGS: &str = "grader.toml";
+
+/// Comment template, see [`crate::template`].
+pub const COMMENT_TEMPLATE: &str = "comment.txt";
+
+/// Numbered deductions, see [`crate::deductions`].
+pub const DEDUCTIONS: &str = "deductions.txt";
+
+/// Canned comments, see [`crate::snippets`].
+pub const SNIPPETS: &str = "snippets.txt";
+
+/// Rhai scripts run as checks, see [`crate::scripts`].
+pub const SCRIPTS: &str = "checks";
+
+/// Input and expected output pairs, see [`crate::harness`].
+pub const TESTS: &str = "tests";
+
+/// Style for `clang-format` and checks for `clang-tidy`, see
+/// [`crate::style`].
+pub const CLANG_FORMAT: &str = ".clang-format";
+pub const CLANG_TIDY: &str = ".clang-tidy";
+
+/// Commands run in each submission, see [`crate::checks`].
+pub const CHECKS: &str = "checks.txt";
+
+/// Build command run before the checks, see [`crate::checks`].
+pub const BUILD: &str = "build.txt";
+
+/// Reference implementation timed checks can be compared against, see
+/// [`crate::bench`].
+pub const REFERENCE: &str = "reference";
+
+/// Instructor files copied over the student's before the checks run, see
+/// [`crate::inject`].
+pub const INJECT: &str = "inject";
+
+/// Instructor files copied only where the student has none.
+pub const DEFAULTS: &str = "defaults";
+
+/// Starter code handed out with the assignment, ignored when looking for
+/// identical files.
+pub const STARTER: &str = "starter";
+
+/// Previous semesters' submissions, one directory or zip per submission.
+pub const ARCHIVE: &str = "archive";
+
+/// The directory of every assignment's directory.
+pub fn root() -> PathBuf {
+    env::var("GRADER_ASSIGNMENTS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("assignments"))
+}
+
+pub fn assignment_dir(assignment_id: Option<usize>, assignment_name: &str) -> Option<PathBuf> {
+    let root = root();
+
+    assignment_id
+        .map(|id| id.to_string())
+        .into_iter()
+        .chain([assignment_name.to_string()])
+        .filter(|name| !name.is_empty())
+        .map(|name| root.join(name))
+        .find(|dir| dir.is_dir())
+}
+
+pub async fn read(dir: Option<&Path>, file: &str) -> Option<String> {
+    tokio::fs::read_to_string(dir?.join(file)).await.ok()
+}
+
+/// The assignment's `grader.toml`. Everything in it is optional, the defaults
+/// suit the C assignments.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// text the readme has to contain, empty to not check for one
+    pub disclaimer: Option<String>,
+    /// patterns of the files checked for the student's name and opened in
+    /// the editor
+    pub files: Vec<String>,
+    /// files the submission has to contain
+    pub required: Vec<String>,
+    /// names of the checks in `checks.txt` to run, all of them when not given
+    pub checks: Option<Vec<String>>,
+    /// runs the student's program on each test's input, see
+    /// [`crate::harness`]
+    pub test_command: Option<String>,
+    /// points per test, by name, 1 when not given
+    pub test_points: HashMap<String, f64>,
+    /// checks the program's memory use on each test, see
+    /// [`crate::memcheck`]
+    pub memcheck: Option<memcheck::Tool>,
+    /// points per memory check
+    pub memcheck_points: f64,
+    /// identifiers and snippets the student's files may not use, see
+    /// [`crate::banned`]
+    pub banned: Vec<String>,
+    /// share of fingerprints from which submissions are flagged as similar,
+    /// see [`crate::similarity`]
+    pub similarity: Option<f64>,
+    /// tools checking the style of the student's C files, see
+    /// [`crate::style`]
+    pub style: Vec<style::Tool>,
+}
+
+impl Settings {
+    /// `files` as one case insensitive pattern.
+    pub fn files_pattern(&self) -> Option<Regex> {
+        match self.files.is_empty() {
+            true => None,
+            false => Regex::new(&format!("(?i){}", self.files.join("|"))).ok(),
+        }
+    }
+}
+
+/// The assignment's settings, the defaults when it has no `grader.toml` or
+/// it can't be read.
+pub async fn settings(dir: Option<&Path>) -> Settings {
+    let Some(contents) = read(dir, SETTINGS).await else {
+        return Settings::default();
+    };
+
+    let settings = match toml::from_str::<Settings>(&contents) {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("{} {}: {}", "warning:".yellow(), SETTINGS, e);
+            return Settings::default();
+        }
+    };
+
+    if !settings.files.is_empty() && settings.files_pattern().is_none() {
+        println!(
+            "{} {}: invalid files pattern, using the defaults",
+            "warning:".yellow(),
+            SETTINGS
+        );
+    }
+
+    settings
+}
@@ -0,0 +1,63 @@
+//! Backtraces for checks that crash, taken by running the command again under
+//! gdb. Frames only have file and line numbers when the build uses `-g`.
+
+use crate::{checks, sandbox};
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+
+const SIGNALS: &[(i32, &str)] = &[
+    (4, "SIGILL"),
+    (6, "SIGABRT"),
+    (7, "SIGBUS"),
+    (8, "SIGFPE"),
+    (11, "SIGSEGV"),
+];
+
+/// The crash signal the command died of, either directly or as the shell's
+/// `128 + signal` exit code.
+pub fn signal(status: ExitStatus) -> Option<&'static str> {
+    let signal = status
+        .signal()
+        .or_else(|| status.code().filter(|&c| c > 128).map(|c| c - 128))?;
+
+    SIGNALS
+        .iter()
+        .find(|(s, _)| *s == signal)
+        .map(|(_, name)| *name)
+}
+
+pub async fn backtrace(
+    command: &str,
+    dir: &Path,
+    mounts: &[PathBuf],
+    backend: sandbox::Backend,
+) -> String {
+    let gdb = format!(
+        "gdb -q -batch -ex 'set follow-fork-mode child' -ex run -ex bt --args sh -c {}",
+        sandbox::quote(command)
+    );
+
+    let mut sandboxed = sandbox::command(&gdb, dir, mounts, backend);
+    let run = sandboxed
+        .command
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+
+    let Ok(Ok(output)) = tokio::time::timeout(checks::timeout(), run).await else {
+        sandboxed.stop();
+        return "no backtrace, is gdb installed?".into();
+    };
+
+    let frames: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| l.starts_with('#') || l.starts_with("Program received"))
+        .map(String::from)
+        .collect();
+
+    match frames.is_empty() {
+        true => "no backtrace, the crash did not happen again under gdb".into(),
+        false => frames.join("\n"),
+    }
+}
@@ -0,0 +1,166 @@
+//! Drops into gdb on the student's program with a failing check's arguments
+//! and input already set, building it first if needed, or traces a check's
+//! system or library calls with strace or ltrace.
+
+use crate::checks::{self, Check, CheckResult};
+use crate::{harness, record, sandbox};
+use colored::Colorize;
+use console::{Key, Term};
+use dialoguer::{theme::ColorfulTheme, Select};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::fs;
+
+/// Follows forks and leaves out the noise of the dynamic loader.
+const STRACE: &str = "strace -f -s 80 -e trace=%process,%file,%desc,%memory,%signal";
+
+const LTRACE: &str = "ltrace -f -s 80";
+
+fn pick<'a>(checks: &[&'a Check]) -> Result<&'a Check, Box<dyn std::error::Error>> {
+    Ok(match checks {
+        [check] => *check,
+        _ => {
+            let names: Vec<_> = checks.iter().map(|c| &c.name).collect();
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Check")
+                .items(&names)
+                .default(0)
+                .interact()?;
+
+            checks[selection]
+        }
+    })
+}
+
+/// Asks for a single key press: `s` or `l` trace any check, passing or not,
+/// and when a check failed `g` debugs it.
+pub async fn offer(
+    checks: &[Check],
+    results: &[CheckResult],
+    dir: &Path,
+    backend: sandbox::Backend,
+    recording: Option<&record::Recording>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let failed: Vec<_> = checks
+        .iter()
+        .zip(results)
+        .filter(|(_, r)| !r.passed)
+        .map(|(c, _)| c)
+        .collect();
+
+    match failed.is_empty() {
+        true => println!(
+            "Press s or l to strace or ltrace a check, any other key to continue"
+        ),
+        false => println!(
+            "Press g to debug a failing check in gdb, s or l to strace or ltrace a check, any other key to continue"
+        ),
+    }
+
+    let tool = match Term::stdout().read_key()? {
+        Key::Char('g') if !failed.is_empty() => {
+            let build = checks.iter().find(|c| c.name == checks::BUILD);
+            return launch(pick(&failed)?, build, dir, backend, recording).await;
+        }
+        Key::Char('s') => STRACE,
+        Key::Char('l') => LTRACE,
+        _ => return Ok(()),
+    };
+
+    let all: Vec<_> = checks.iter().collect();
+    match trace(pick(&all)?, tool, dir, backend).await {
+        Ok(path) => println!("Trace saved to {}", path.display()),
+        Err(e) => println!("{} {}", "warning:".yellow(), e),
+    }
+
+    Ok(())
+}
+
+/// Runs the check under `tool`, saving the trace next to the student's
+/// directory so it is kept with their feedback. The trace is written inside
+/// the directory, the only place the sandbox can write, then moved out.
+pub async fn trace(
+    check: &Check,
+    tool: &str,
+    dir: &Path,
+    backend: sandbox::Backend,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let name = tool.split_whitespace().next().unwrap_or(tool);
+
+    let mut path = dir.canonicalize()?.into_os_string();
+    path.push(format!(".{name}.txt"));
+    let path = PathBuf::from(path);
+
+    let report = checks::report_path(dir, name);
+    let command = format!(
+        "{} -o {} sh -c {}",
+        tool,
+        sandbox::quote(&report.to_string_lossy()),
+        sandbox::quote(&check.command)
+    );
+
+    checks::execute(&command, dir, &check.mounts, backend).await?;
+    fs::rename(&report, &path)
+        .await
+        .map_err(|_| format!("{name} wrote no trace, is it installed?"))?;
+
+    Ok(path)
+}
+
+/// Opens gdb on the program the check runs, e.g. `./prog -v < input.txt`
+/// becomes `gdb ./prog` with `set args -v < input.txt`. A test runs the
+/// program on its input, without comparing the output; other pipelines can't
+/// be debugged.
+pub async fn launch(
+    check: &Check,
+    build: Option<&Check>,
+    dir: &Path,
+    backend: sandbox::Backend,
+    recording: Option<&record::Recording>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let command = harness::program_command(check)
+        .unwrap_or(&check.command)
+        .trim();
+
+    if command.contains('|') {
+        println!(
+            "{} pipes into another program, only simple commands can be debugged",
+            check.name
+        );
+        return Ok(());
+    }
+
+    let (program, args) = command
+        .split_once(char::is_whitespace)
+        .unwrap_or((command, ""));
+
+    if !dir.join(program).is_file() {
+        let build = match build {
+            Some(build) => build.command.as_str(),
+            None => "make",
+        };
+
+        println!("Building with {build}...");
+        checks::execute(build, dir, &[], backend).await?;
+    }
+
+    if !dir.join(program).is_file() {
+        println!("{program} not found, only simple commands can be debugged");
+        return Ok(());
+    }
+
+    let gdb = format!(
+        "gdb -q -ex {} {}",
+        sandbox::quote(&format!("set args {args}")),
+        sandbox::quote(program)
+    );
+
+    // blocking, like the shell, so gdb gets the terminal
+    sandbox::interactive(&gdb, dir, &check.mounts, backend, recording)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()?;
+
+    Ok(())
+}
@@ -0,0 +1,179 @@
+//! Numbered deductions applied with a single key press while grading.
+//!
+//! The assignment's `deductions.txt` has one deduction per line, e.g.
+//! `-5 no Makefile` or `2: -10 memory leak`. A deduction numbered like `2:`
+//! is toggled by that key, the rest by the keys left over, in file order.
+
+use colored::Colorize;
+use console::{Key, Term};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+
+const KEYS: &str = "123456789abcdefghijklmnopqrstuvwxyz";
+
+static LINE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:(\w+)\s*:\s*)?([+-]?\d+(?:\.\d+)?)\s+(.+)$").unwrap());
+
+#[derive(Debug)]
+pub struct Deduction {
+    pub key: char,
+    pub points: f64,
+    pub description: String,
+}
+
+/// The key a deduction is numbered with, e.g. `2` for `2: -10 memory leak`.
+fn key(number: &str) -> Option<char> {
+    let mut chars = number.chars();
+    let key = chars.next()?.to_ascii_lowercase();
+
+    (chars.next().is_none() && KEYS.contains(key)).then_some(key)
+}
+
+pub fn parse(contents: &str) -> Vec<Deduction> {
+    let lines: Vec<_> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| LINE.captures(l))
+        .filter_map(|caps| {
+            Some((
+                caps.get(1).and_then(|n| key(n.as_str())),
+                caps[2].parse().ok()?,
+                caps[3].trim().to_string(),
+            ))
+        })
+        .collect();
+
+    // a number used twice only keys its first deduction
+    let mut taken = HashSet::new();
+    let keys: Vec<_> = lines
+        .iter()
+        .map(|(key, _, _)| key.filter(|&k| taken.insert(k)))
+        .collect();
+    let mut free = KEYS.chars().filter(|k| !taken.contains(k));
+
+    lines
+        .into_iter()
+        .zip(keys)
+        .filter_map(|((_, points, description), key)| {
+            Some(Deduction {
+                key: key.or_else(|| free.next())?,
+                points,
+                description,
+            })
+        })
+        .collect()
+}
+
+pub fn score(applied: &[&Deduction], points_possible: f64) -> f64 {
+    points_possible + applied.iter().map(|d| d.points).sum::<f64>()
+}
+
+pub fn itemize(applied: &[&Deduction]) -> String {
+    applied
+        .iter()
+        .map(|d| format!("{:+} {}", d.points, d.description))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Toggles deductions by key press until enter is pressed.
+pub fn select(
+    deductions: &[Deduction],
+    points_possible: Option<f64>,
+) -> Result<Vec<&Deduction>, Box<dyn std::error::Error>> {
+    println!("Deductions:");
+
+    for d in deductions {
+        println!(
+            "\t{} {:>+6} {}",
+            d.key.to_string().bold(),
+            d.points,
+            d.description
+        );
+    }
+
+    println!("Press a key to toggle a deduction, enter when done");
+
+    let term = Term::stdout();
+    let mut applied = vec![false; deductions.len()];
+
+    loop {
+        let i = match term.read_key()? {
+            Key::Enter => break,
+            Key::Char(c) => match deductions.iter().position(|d| d.key == c) {
+                Some(i) => i,
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        applied[i] = !applied[i];
+
+        let selected: Vec<_> = deductions
+            .iter()
+            .zip(&applied)
+            .filter_map(|(d, &a)| a.then_some(d))
+            .collect();
+
+        let total = match points_possible {
+            Some(points) => format!("{}/{}", score(&selected, points), points),
+            None => format!("{:+}", score(&selected, 0.0)),
+        };
+
+        println!(
+            "\t{} {:+} {} ({})",
+            if applied[i] { "+".red() } else { "-".green() },
+            deductions[i].points,
+            deductions[i].description,
+            total
+        );
+    }
+
+    Ok(deductions
+        .iter()
+        .zip(applied)
+        .filter_map(|(d, a)| a.then_some(d))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keys_by_number() {
+        let deductions = parse("# comment\n-5 no Makefile\n2: -10 memory leak\nx: +1 extra\n");
+
+        let keys: Vec<_> = deductions
+            .iter()
+            .map(|d| (d.key, d.points, d.description.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ('1', -5.0, "no Makefile"),
+                ('2', -10.0, "memory leak"),
+                ('x', 1.0, "extra")
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_gives_unnumbered_and_repeated_the_keys_left() {
+        let deductions = parse("1: -1 a\n-2 b\n1: -3 c\nleak: -4 d\n");
+
+        let keys: Vec<_> = deductions.iter().map(|d| d.key).collect();
+        assert_eq!(keys, ['1', '2', '3', '4']);
+    }
+
+    #[test]
+    fn score_adds_the_applied_points() {
+        let deductions = parse("-5 a\n-2.5 b\n+1 c\n");
+        let applied: Vec<_> = deductions.iter().collect();
+
+        assert_eq!(score(&applied, 100.0), 93.5);
+        assert_eq!(score(&[], 100.0), 100.0);
+    }
+}
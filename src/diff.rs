@@ -0,0 +1,110 @@
+//! What changed between two of a student's attempts, e.g. in a resubmission,
+//! as a colored unified diff of each file, and what a submission changed
+//! from the starter code.
+
+use crate::{canvas, student_submission};
+use canvasapi::models::assignment::Assignment;
+use chrono::Local;
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Select};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+fn colorize(line: &str) -> String {
+    match line {
+        l if l.starts_with("diff ") || l.starts_with("+++") || l.starts_with("---") => {
+            l.bold().to_string()
+        }
+        l if l.starts_with("@@") => l.cyan().to_string(),
+        l if l.starts_with('+') => l.green().to_string(),
+        l if l.starts_with('-') => l.red().to_string(),
+        l => l.to_string(),
+    }
+}
+
+/// A unified diff of every file under the two directories.
+pub async fn between(old: &Path, new: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let output = Command::new("diff")
+        .args(["-ruN", "-x", ".git"])
+        .arg(old)
+        .arg(new)
+        .output()
+        .await?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub async fn run(
+    client: &canvas::Client,
+    course_id: usize,
+    assignment: &Assignment,
+    student: &canvas::User,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let attempts = client
+        .get_submission_history(course_id, assignment.id, student.id)
+        .await?;
+
+    if attempts.len() < 2 {
+        println!("{} has only one attempt", student.sortable_name);
+        return Ok(());
+    }
+
+    let items: Vec<_> = attempts
+        .iter()
+        .map(|a| {
+            format!(
+                "Attempt {} ({})",
+                a.attempt.map(|n| n.to_string()).unwrap_or_default(),
+                a.submitted_at
+                    .map(|d| d.with_timezone(&Local).format("%a %b %e %H:%M").to_string())
+                    .unwrap_or_default()
+            )
+        })
+        .collect();
+
+    let theme = ColorfulTheme::default();
+    let old = Select::with_theme(&theme)
+        .with_prompt("Old attempt")
+        .items(&items)
+        .default(attempts.len() - 2)
+        .interact()?;
+    let new = Select::with_theme(&theme)
+        .with_prompt("New attempt")
+        .items(&items)
+        .default(attempts.len() - 1)
+        .interact()?;
+
+    let root = PathBuf::from(&student.sortable_name);
+    let mut dirs = vec![];
+
+    for i in [old, new] {
+        let Some(mut submission) =
+            student_submission(client, course_id, assignment, student).await?
+        else {
+            return Ok(());
+        };
+
+        let path = root.join(format!("attempt-{}", attempts[i].attempt.unwrap_or(i + 1)));
+        submission.attempt = Some(attempts[i].clone());
+        submission
+            .download_submission(
+                client,
+                course_id,
+                assignment.id,
+                student.sortable_name.clone(),
+                path.clone(),
+            )
+            .await?;
+
+        dirs.push(path);
+    }
+
+    let diff = between(&dirs[0], &dirs[1]).await?;
+
+    match diff.trim().is_empty() {
+        true => println!("No changes"),
+        false => diff.lines().for_each(|l| println!("{}", colorize(l))),
+    }
+
+    Ok(())
+}
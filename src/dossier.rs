@@ -0,0 +1,55 @@
+//! Every submission one student made across several assignments, gathered
+//! under one directory for integrity investigations or incomplete grades.
+
+use crate::{canvas, checks, config, download_student, inject, integrity, roster, Options};
+use canvasapi::models::assignment::Assignment;
+use colored::Colorize;
+use std::path::PathBuf;
+
+pub async fn run(
+    client: &canvas::Client,
+    course_id: usize,
+    assignments: &[&Assignment],
+    student: &canvas::User,
+    roster: &roster::Roster,
+    options: Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let root = PathBuf::from(&student.sortable_name);
+
+    for assignment in assignments {
+        let assignment_name = assignment.name.clone().unwrap_or_default();
+
+        println!("{}", assignment_name.bright_blue());
+
+        let path = root.join(assignment_name.replace('/', "-"));
+
+        let Some(d) =
+            download_student(client, course_id, assignment, student, roster, path).await?
+        else {
+            println!("\tNo submission");
+            continue;
+        };
+
+        println!("Submission is {}", d.lateness);
+        integrity::print_notes(&d.notes);
+
+        let assignment_dir = config::assignment_dir(Some(assignment.id), &assignment_name);
+        let checks = checks::load(assignment_dir.as_deref(), options.build_by_default).await;
+
+        let snapshot = inject::Snapshot::take(&d.path);
+        let injected = inject::inject(assignment_dir.as_deref(), &d.path).await?;
+        inject::print(&injected, &d.path);
+
+        let results = checks::run_all(&checks, &d.path, options.sandbox).await?;
+
+        if !results.is_empty() {
+            checks::print(&results);
+        }
+
+        snapshot.restore(&injected).await?;
+    }
+
+    println!("Submissions saved to {}", root.display());
+
+    Ok(())
+}
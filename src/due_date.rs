@@ -0,0 +1,194 @@
+use crate::canvas;
+use chrono::{DateTime, Duration, Utc};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Per-student due dates for an assignment, taking overrides into account.
+#[derive(Debug)]
+pub struct DueDates {
+    base: Option<DateTime<Utc>>,
+    /// `None` for students an override gives no due date
+    overridden: HashMap<usize, Option<DateTime<Utc>>>,
+}
+
+impl DueDates {
+    pub async fn fetch(
+        client: &canvas::Client,
+        course_id: usize,
+        assignment_id: usize,
+        base: Option<DateTime<Utc>>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let overrides = client
+            .get_assignment_overrides(course_id, assignment_id)
+            .await?;
+
+        let mut sections: HashMap<usize, Vec<usize>> = HashMap::new();
+        if overrides.iter().any(|o| o.course_section_id.is_some()) {
+            for enrollment in client.get_student_enrollments(course_id).await? {
+                sections
+                    .entry(enrollment.course_section_id)
+                    .or_default()
+                    .push(enrollment.user_id);
+            }
+        }
+
+        let mut overridden = HashMap::new();
+
+        for o in overrides {
+            // the override leaves the due date alone
+            let Some(due_at) = o.due_at else {
+                continue;
+            };
+
+            let students = if let Some(ids) = o.student_ids {
+                ids
+            } else if let Some(group_id) = o.group_id {
+                client
+                    .get_group_members(group_id)
+                    .await?
+                    .into_iter()
+                    .map(|m| m.id)
+                    .collect()
+            } else if let Some(section_id) = o.course_section_id {
+                sections.get(&section_id).cloned().unwrap_or_default()
+            } else {
+                vec![]
+            };
+
+            // canvas gives the student the most lenient of their overrides,
+            // no due date being the most lenient
+            for id in students {
+                let due = overridden.entry(id).or_insert(due_at);
+                *due = match (*due, due_at) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    _ => None,
+                };
+            }
+        }
+
+        Ok(Self { base, overridden })
+    }
+
+    pub fn for_user(&self, user_id: usize) -> Option<DateTime<Utc>> {
+        self.overridden.get(&user_id).copied().unwrap_or(self.base)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Lateness {
+    Unsubmitted,
+    NoDueDate,
+    OnTime,
+    Late(Duration),
+    /// late by an unknown amount, e.g. from the file names of a bulk download
+    Marked,
+}
+
+impl Lateness {
+    pub fn new(submitted_at: Option<DateTime<Utc>>, due_at: Option<DateTime<Utc>>) -> Self {
+        match (submitted_at, due_at) {
+            (None, _) => Lateness::Unsubmitted,
+            (Some(_), None) => Lateness::NoDueDate,
+            (Some(submitted), Some(due)) if submitted > due => Lateness::Late(submitted - due),
+            (Some(_), Some(_)) => Lateness::OnTime,
+        }
+    }
+
+    /// Percent of the score the course's late policy takes off.
+    pub fn penalty(&self, policy: &canvas::LatePolicy) -> f64 {
+        let Lateness::Late(by) = self else {
+            return 0.0;
+        };
+
+        if !policy.late_submission_deduction_enabled {
+            return 0.0;
+        }
+
+        let interval = match policy.late_submission_interval.as_str() {
+            "hour" => Duration::hours(1),
+            _ => Duration::days(1),
+        };
+
+        // any part of an interval counts as a whole one
+        let intervals = (by.num_seconds() + interval.num_seconds() - 1) / interval.num_seconds();
+        let penalty = intervals as f64 * policy.late_submission_deduction;
+
+        let max = match policy.late_submission_minimum_percent_enabled {
+            true => 100.0 - policy.late_submission_minimum_percent,
+            false => 100.0,
+        };
+
+        penalty.min(max)
+    }
+}
+
+impl fmt::Display for Lateness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lateness::Unsubmitted => write!(f, "{}", "not submitted".yellow()),
+            Lateness::NoDueDate => write!(f, "{}", "no due date".normal()),
+            Lateness::OnTime => write!(f, "{}", "on time".green()),
+            Lateness::Marked => write!(f, "{}", "marked late".red()),
+            Lateness::Late(by) => {
+                write!(f, "{}", format!("late by {}", format_duration(*by)).red())
+            }
+        }
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let days = d.num_days();
+    let hours = d.num_hours() % 24;
+    let minutes = d.num_minutes() % 60;
+
+    match (days, hours) {
+        (0, 0) => format!("{minutes}m"),
+        (0, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h {minutes}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(interval: &str) -> canvas::LatePolicy {
+        canvas::LatePolicy {
+            late_submission_deduction_enabled: true,
+            late_submission_deduction: 10.0,
+            late_submission_interval: interval.into(),
+            late_submission_minimum_percent_enabled: true,
+            late_submission_minimum_percent: 50.0,
+        }
+    }
+
+    #[test]
+    fn penalty_counts_started_intervals() {
+        let day = policy("day");
+        assert_eq!(Lateness::Late(Duration::seconds(1)).penalty(&day), 10.0);
+        assert_eq!(Lateness::Late(Duration::hours(25)).penalty(&day), 20.0);
+        assert_eq!(
+            Lateness::Late(Duration::minutes(90)).penalty(&policy("hour")),
+            20.0
+        );
+    }
+
+    #[test]
+    fn penalty_stops_at_the_minimum_percent() {
+        assert_eq!(
+            Lateness::Late(Duration::days(10)).penalty(&policy("day")),
+            50.0
+        );
+    }
+
+    #[test]
+    fn penalty_only_when_late_and_enabled() {
+        let mut day = policy("day");
+        assert_eq!(Lateness::OnTime.penalty(&day), 0.0);
+        assert_eq!(Lateness::Marked.penalty(&day), 0.0);
+
+        day.late_submission_deduction_enabled = false;
+        assert_eq!(Lateness::Late(Duration::days(1)).penalty(&day), 0.0);
+    }
+}
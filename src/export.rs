@@ -0,0 +1,126 @@
+//! Writes the scores recorded in the session file as a canvas gradebook
+//! import csv, for courses where grades can't be posted through the API.
+//! Canvas doesn't import comments, so they go to a second csv beside it.
+
+use crate::{canvas, resume};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+pub async fn run(client: &canvas::Client, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let progress = resume::load().await.ok_or("no grading session to export")?;
+
+    let course_id = progress.course_id;
+    let students: HashMap<_, _> = client
+        .get_course_students(course_id)
+        .await?
+        .into_iter()
+        .map(|s| (s.id, s))
+        .collect();
+
+    let section_names: HashMap<_, _> = client
+        .get_sections(course_id)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|s| (s.id, s.name))
+        .collect();
+    let mut sections: HashMap<usize, Vec<&str>> = HashMap::new();
+    for e in client
+        .get_student_enrollments(course_id)
+        .await
+        .unwrap_or_default()
+    {
+        if let Some(name) = section_names.get(&e.course_section_id) {
+            sections.entry(e.user_id).or_default().push(name);
+        }
+    }
+
+    // canvas matches columns to assignments by the id in parentheses
+    let columns: Vec<_> = client
+        .get_assignments(course_id)
+        .await?
+        .into_iter()
+        .filter(|a| progress.assignment_ids.contains(&a.id))
+        .map(|a| {
+            (
+                a.id,
+                format!("{} ({})", a.name.as_deref().unwrap_or_default(), a.id),
+            )
+        })
+        .collect();
+
+    let scores: HashMap<_, _> = progress
+        .students
+        .iter()
+        .filter_map(|s| Some(((s.assignment_id, s.user_id), s.score?)))
+        .collect();
+    let user_ids: BTreeSet<_> = scores.keys().map(|&(_, user_id)| user_id).collect();
+
+    let mut writer = csv::Writer::from_path(output)?;
+
+    writer.write_record(
+        ["Student", "ID", "SIS User ID", "SIS Login ID", "Section"]
+            .into_iter()
+            .chain(columns.iter().map(|(_, name)| name.as_str())),
+    )?;
+
+    for user_id in &user_ids {
+        let student = students.get(user_id);
+
+        let mut record = vec![
+            student.map(|s| s.sortable_name.clone()).unwrap_or_default(),
+            user_id.to_string(),
+            student
+                .and_then(|s| s.sis_user_id.clone())
+                .unwrap_or_default(),
+            student.and_then(|s| s.login_id.clone()).unwrap_or_default(),
+            sections
+                .get(user_id)
+                .map(|s| s.join(", "))
+                .unwrap_or_default(),
+        ];
+        record.extend(columns.iter().map(|(id, _)| {
+            scores
+                .get(&(*id, *user_id))
+                .map(f64::to_string)
+                .unwrap_or_default()
+        }));
+
+        writer.write_record(&record)?;
+    }
+
+    writer.flush()?;
+    println!("{} scores written to {}", user_ids.len(), output.display());
+
+    let comments: Vec<_> = progress
+        .students
+        .iter()
+        .filter_map(|s| Some((s, s.comment.as_deref()?)))
+        .collect();
+
+    if !comments.is_empty() {
+        let path = output.with_extension("comments.csv");
+        let mut writer = csv::Writer::from_path(&path)?;
+
+        writer.write_record(["Student", "ID", "Assignment", "Comment"])?;
+
+        for (s, comment) in comments {
+            let name = students
+                .get(&s.user_id)
+                .map(|s| s.sortable_name.as_str())
+                .unwrap_or_default();
+            let assignment = columns
+                .iter()
+                .find(|(id, _)| *id == s.assignment_id)
+                .map(|(_, name)| name.as_str())
+                .unwrap_or_default();
+
+            writer.write_record([name, s.user_id.to_string().as_str(), assignment, comment])?;
+        }
+
+        writer.flush()?;
+        println!("Comments written to {}", path.display());
+    }
+
+    Ok(())
+}
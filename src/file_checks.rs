@@ -0,0 +1,260 @@
+//! Checks of the files as the student submitted them, before anything is
+//! injected or built, e.g. that they include the student's name. Each check
+//! is registered in [`registry`] and its result printed the same way.
+
+use crate::{banned, config, scripts, style, DownloadedSubmission, File, Session, TEXT_ENTRY};
+use colored::Colorize;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+const README_DISCLAIMER: &str =
+    "by submitting this file to carmen, i certify that i have performed all";
+
+/// Files checked for the student's name and opened in the editor, unless
+/// `grader.toml` says otherwise.
+const FILES_OF_INTEREST: &str = r"(readme)|(\.c)|(\.h)|(makefile)|(submission\.txt)";
+
+pub trait FileCheck {
+    fn run(&self, files: &[File]) -> FileFinding;
+}
+
+pub struct FileFinding {
+    /// e.g. "File contains name"
+    pub title: &'static str,
+    pub items: Vec<Item>,
+}
+
+/// One file, or other thing, a check looked at.
+pub struct Item {
+    pub name: String,
+    /// e.g. "readme.txt includes your name", for templates and feedback drafts
+    pub description: String,
+    pub passed: bool,
+}
+
+impl FileFinding {
+    pub fn print(&self) {
+        if self.items.is_empty() {
+            return;
+        }
+
+        println!("{}:", self.title);
+
+        for item in &self.items {
+            let passed = match item.passed {
+                true => "✔".green(),
+                false => "✗".red(),
+            };
+
+            println!("\t{} {}", passed, item.name);
+        }
+    }
+
+    pub fn summary(&self) -> impl Iterator<Item = (String, bool)> + '_ {
+        self.items.iter().map(|i| (i.description.clone(), i.passed))
+    }
+}
+
+/// The assignment's files of interest, matched against lower case names.
+pub fn files_of_interest(settings: &config::Settings) -> Regex {
+    settings
+        .files_pattern()
+        .unwrap_or_else(|| Regex::new(FILES_OF_INTEREST).unwrap())
+}
+
+/// The checks run on a submission, in the order they are shown. `names` are
+/// the ways the student may have written their name.
+pub fn registry<'a>(
+    session: &'a Session,
+    submission: &DownloadedSubmission,
+    names: Vec<String>,
+) -> Vec<Box<dyn FileCheck + 'a>> {
+    let settings = &session.settings;
+
+    let mut checks: Vec<Box<dyn FileCheck + 'a>> = vec![Box::new(ContainsName {
+        names,
+        files: files_of_interest(settings),
+    })];
+
+    let disclaimer = settings
+        .disclaimer
+        .as_deref()
+        .unwrap_or(README_DISCLAIMER)
+        .to_lowercase();
+
+    if !disclaimer.is_empty() {
+        checks.push(Box::new(ContainsDisclaimer { disclaimer }));
+    }
+
+    if !settings.required.is_empty() {
+        checks.push(Box::new(RequiredFiles {
+            dir: submission.path.clone(),
+            names: settings.required.clone(),
+        }));
+    }
+
+    if !settings.banned.is_empty() {
+        checks.push(Box::new(banned::Banned::new(
+            &settings.banned,
+            files_of_interest(settings),
+        )));
+    }
+
+    if !settings.style.is_empty() {
+        checks.push(Box::new(style::StyleCheck {
+            tools: settings.style.clone(),
+            assignment_dir: session.assignment_dir.clone(),
+            dir: submission.path.clone(),
+        }));
+    }
+
+    if !session.scripts.is_empty() {
+        checks.push(Box::new(scripts::ScriptChecks::new(
+            &session.scripts,
+            submission,
+        )));
+    }
+
+    checks
+}
+
+struct ContainsName {
+    names: Vec<String>,
+    files: Regex,
+}
+
+impl FileCheck for ContainsName {
+    fn run(&self, files: &[File]) -> FileFinding {
+        FileFinding {
+            title: "File contains name",
+            items: files
+                .iter()
+                .filter(|f| self.files.is_match(&f.name.to_lowercase()))
+                .map(|f| {
+                    let contents = f.contents.as_deref().unwrap_or_default().to_lowercase();
+
+                    Item {
+                        name: f.name.clone(),
+                        description: format!("{} includes your name", f.name),
+                        passed: self.names.iter().any(|n| contents.contains(n.as_str())),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+struct ContainsDisclaimer {
+    /// lower case
+    disclaimer: String,
+}
+
+impl FileCheck for ContainsDisclaimer {
+    fn run(&self, files: &[File]) -> FileFinding {
+        FileFinding {
+            title: "File contains readme disclaimer",
+            items: files
+                .iter()
+                .filter(|f| f.name.to_lowercase().contains("readme") || f.name == TEXT_ENTRY)
+                .map(|f| Item {
+                    name: f.name.clone(),
+                    description: format!("{} includes the submission disclaimer", f.name),
+                    passed: f
+                        .contents
+                        .as_deref()
+                        .unwrap_or_default()
+                        .to_lowercase()
+                        .contains(&self.disclaimer),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The required files missing from the submission in `dir`. Names match
+/// regardless of case and may use `*`, e.g. `README*`, and match a file in
+/// a subdirectory by its name alone.
+pub fn missing<'a>(dir: &Path, names: &'a [String]) -> Vec<&'a String> {
+    let paths: Vec<_> = WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(Result::ok)
+        .filter(|e| e.depth() > 0)
+        .filter_map(|e| Some(e.path().strip_prefix(dir).ok()?.to_path_buf()))
+        .collect();
+
+    names
+        .iter()
+        .filter(|name| {
+            let pattern = format!("(?i)^{}$", regex::escape(name).replace(r"\*", "[^/]*"));
+            let Ok(re) = Regex::new(&pattern) else {
+                return !dir.join(name).exists();
+            };
+
+            !paths.iter().any(|p| {
+                re.is_match(&p.to_string_lossy())
+                    || p.file_name()
+                        .is_some_and(|n| re.is_match(&n.to_string_lossy()))
+            })
+        })
+        .collect()
+}
+
+/// Students whose submissions are missing required files, shown once
+/// everything is downloaded so incomplete ones stand out before grading.
+pub fn print_missing(settings: &config::Settings, submissions: &[DownloadedSubmission]) {
+    if settings.required.is_empty() || submissions.is_empty() {
+        return;
+    }
+
+    let incomplete: Vec<_> = submissions
+        .iter()
+        .map(|s| (s, missing(&s.path, &settings.required)))
+        .filter(|(_, missing)| !missing.is_empty())
+        .collect();
+
+    if incomplete.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "All {} submissions have the required files",
+                submissions.len()
+            )
+            .green()
+        );
+        return;
+    }
+
+    println!("Missing files:");
+
+    for (s, missing) in incomplete {
+        let names: Vec<_> = missing.iter().map(|n| n.as_str()).collect();
+        println!("\t{}: {}", s.name.bright_blue(), names.join(", ").red());
+    }
+}
+
+/// Looks in the submission's directory, so files in subdirectories count.
+struct RequiredFiles {
+    dir: PathBuf,
+    names: Vec<String>,
+}
+
+impl FileCheck for RequiredFiles {
+    fn run(&self, _files: &[File]) -> FileFinding {
+        let missing = missing(&self.dir, &self.names);
+
+        FileFinding {
+            title: "Required files",
+            items: self
+                .names
+                .iter()
+                .map(|name| Item {
+                    name: name.clone(),
+                    description: format!("{} was submitted", name),
+                    passed: !missing.contains(&name),
+                })
+                .collect(),
+        }
+    }
+}
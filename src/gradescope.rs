@@ -0,0 +1,76 @@
+//! Check results in gradescope's autograder `results.json` format, so the
+//! same `checks.txt` can run as a gradescope autograder.
+
+use crate::checks::{self, Check, CheckResult};
+use crate::sandbox;
+use serde::Serialize;
+use std::path::Path;
+
+/// When students see the results, as gradescope names it.
+#[derive(Debug, Clone, Copy, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    #[default]
+    Visible,
+    Hidden,
+    AfterDueDate,
+    AfterPublished,
+}
+
+#[derive(Serialize)]
+struct Results {
+    score: f64,
+    visibility: Visibility,
+    tests: Vec<Test>,
+}
+
+#[derive(Serialize)]
+struct Test {
+    name: String,
+    score: f64,
+    max_score: f64,
+    output: String,
+    visibility: Visibility,
+}
+
+fn results(results: &[CheckResult], visibility: Visibility) -> Results {
+    Results {
+        score: results.iter().map(CheckResult::score).sum(),
+        visibility,
+        tests: results
+            .iter()
+            .map(|r| Test {
+                name: r.name.clone(),
+                score: r.score(),
+                max_score: r.points,
+                output: r.output.clone(),
+                visibility,
+            })
+            .collect(),
+    }
+}
+
+/// Runs the checks in `dir` and writes their results to `output`, shown to
+/// students as `visibility` says.
+pub async fn run(
+    checks: &[Check],
+    dir: &Path,
+    output: &Path,
+    visibility: Visibility,
+    backend: sandbox::Backend,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let checked = checks::run_all(checks, dir, backend).await?;
+    checks::print(&checked);
+
+    if let Some(parent) = output.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    tokio::fs::write(
+        output,
+        serde_json::to_string_pretty(&results(&checked, visibility))?,
+    )
+    .await?;
+
+    Ok(())
+}
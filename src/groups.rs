@@ -0,0 +1,79 @@
+//! Group assignments, where canvas has a submission per member but each group
+//! is graded once and the grade and comment go to every member.
+
+use crate::canvas;
+use canvasapi::prelude::Submission;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone)]
+pub struct GroupSubmission {
+    pub name: String,
+    /// every member's user id, including the one whose submission is graded
+    pub members: Vec<usize>,
+    /// the sortable names of the members still in the course
+    pub names: Vec<String>,
+}
+
+/// Keeps one submission per group, a member's that was submitted if any.
+/// Returns the kept submissions and the group of each one's user.
+pub fn collapse(
+    submissions: Vec<Submission>,
+    groups: &[canvas::SubmissionGroup],
+    students: &[canvas::User],
+) -> (Vec<Submission>, HashMap<usize, GroupSubmission>) {
+    let group_of: HashMap<_, _> = groups
+        .iter()
+        .filter_map(|s| {
+            let group = s.group.as_ref()?;
+            Some((
+                s.user_id,
+                (group.id?, group.name.clone().unwrap_or_default()),
+            ))
+        })
+        .collect();
+
+    let mut kept = vec![];
+    let mut by_group: BTreeMap<usize, (String, Vec<Submission>)> = BTreeMap::new();
+
+    for s in submissions {
+        match s.user_id.and_then(|id| group_of.get(&id)) {
+            Some((id, name)) => by_group
+                .entry(*id)
+                .or_insert_with(|| (name.clone(), vec![]))
+                .1
+                .push(s),
+            None => kept.push(s),
+        }
+    }
+
+    let mut collapsed = HashMap::new();
+
+    for (_, (name, mut members)) in by_group {
+        let ids: Vec<_> = members.iter().filter_map(|s| s.user_id).collect();
+        let names = students
+            .iter()
+            .filter(|s| ids.contains(&s.id))
+            .map(|s| s.sortable_name.clone())
+            .collect();
+        let i = members
+            .iter()
+            .position(|s| s.submitted_at.is_some())
+            .unwrap_or(0);
+        let submission = members.swap_remove(i);
+
+        if let Some(user_id) = submission.user_id {
+            collapsed.insert(
+                user_id,
+                GroupSubmission {
+                    name,
+                    members: ids,
+                    names,
+                },
+            );
+        }
+
+        kept.push(submission);
+    }
+
+    (kept, collapsed)
+}
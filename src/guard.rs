@@ -0,0 +1,120 @@
+//! Last line of defense for outgoing comments: catches other students' names
+//! and emails (usually from a copy pasted snippet) and any flagged words.
+
+use crate::canvas;
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use regex::{Regex, RegexBuilder};
+use std::env;
+
+struct Identity {
+    user_id: usize,
+    name: String,
+    patterns: Vec<Regex>,
+}
+
+pub struct Guard {
+    students: Vec<Identity>,
+    flagged: Vec<Regex>,
+}
+
+fn word(term: &str) -> Option<Regex> {
+    let term = term.trim();
+
+    if term.is_empty() {
+        return None;
+    }
+
+    RegexBuilder::new(&format!(r"\b{}\b", regex::escape(term)))
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+impl Guard {
+    /// Flagged words are read one per line from the file in
+    /// `GRADER_FLAGGED_WORDS`, if set.
+    pub async fn new(students: &[canvas::User]) -> Result<Self, Box<dyn std::error::Error>> {
+        let flagged = match env::var("GRADER_FLAGGED_WORDS") {
+            Ok(path) => tokio::fs::read_to_string(path)
+                .await?
+                .lines()
+                .filter_map(word)
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        let students = students
+            .iter()
+            .map(|s| {
+                // "Last, First" is also matched as "First Last"
+                let reversed = s
+                    .sortable_name
+                    .split_once(',')
+                    .map(|(last, first)| format!("{} {}", first.trim(), last.trim()));
+
+                Identity {
+                    user_id: s.id,
+                    name: s.sortable_name.clone(),
+                    patterns: [
+                        Some(s.name.as_str()),
+                        Some(s.sortable_name.as_str()),
+                        reversed.as_deref(),
+                        s.login_id.as_deref(),
+                        s.email.as_deref(),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(word)
+                    .collect(),
+                }
+            })
+            .collect();
+
+        Ok(Self { students, flagged })
+    }
+
+    fn findings(&self, user_ids: &[usize], text: &str) -> Vec<String> {
+        let others = self
+            .students
+            .iter()
+            .filter(|s| !user_ids.contains(&s.user_id))
+            .filter_map(|s| {
+                s.patterns
+                    .iter()
+                    .find_map(|p| p.find(text))
+                    .map(|m| format!("mentions {} ({:?})", s.name.bright_blue(), m.as_str()))
+            });
+
+        let flagged = self
+            .flagged
+            .iter()
+            .filter_map(|p| p.find(text))
+            .map(|m| format!("contains flagged word {:?}", m.as_str()));
+
+        others.chain(flagged).collect()
+    }
+
+    /// Returns `true` when the comment is clean or the grader confirmed it
+    /// anyway. `user_ids` are the students it is for, a group's members.
+    pub fn review(
+        &self,
+        user_ids: &[usize],
+        text: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let findings = self.findings(user_ids, text);
+
+        if findings.is_empty() {
+            return Ok(true);
+        }
+
+        for finding in &findings {
+            println!("\t{} {}", "!".red(), finding);
+        }
+
+        Ok(Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Comment may leak information, use it anyway?")
+            .default(false)
+            .interact()?)
+    }
+}
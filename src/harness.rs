@@ -0,0 +1,121 @@
+//! Functional tests from the assignment's `tests/` directory: each
+//! `<name>.in` is fed to `test_command` from `grader.toml` and its output
+//! compared with `<name>.out`, ignoring trailing whitespace. Tests are run
+//! as checks worth `test_points.<name>` (default 1), their diff kept as the
+//! output.
+
+use crate::checks::{Check, Kind};
+use crate::config;
+use crate::sandbox::quote;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the tests' check names, to tell them apart from `checks.txt`.
+pub const PREFIX: &str = "test ";
+
+/// What a test's command pipes the program's output through.
+const COMPARE: &str = " 2>/dev/null | diff -u -Z --label expected --label output";
+
+/// The tests' inputs that have an expected output, by name.
+pub fn inputs(assignment_dir: Option<&Path>) -> Vec<PathBuf> {
+    // absolute, since batch checks run in a scratch copy of the submission
+    let Some(dir) = assignment_dir
+        .map(|d| d.join(config::TESTS))
+        .and_then(|d| std::fs::canonicalize(d).ok())
+    else {
+        return vec![];
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return vec![];
+    };
+
+    let mut inputs: Vec<_> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "in"))
+        .filter(|p| p.with_extension("out").is_file())
+        .collect();
+    inputs.sort();
+    inputs
+}
+
+/// The part of a test's command running the program on the test's input,
+/// e.g. `./prog < tests/1.in`, or `None` when the check isn't a test.
+pub fn program_command(check: &Check) -> Option<&str> {
+    check.name.strip_prefix(PREFIX)?;
+
+    check
+        .command
+        .rsplit_once(COMPARE)
+        .map(|(program, _)| program)
+}
+
+/// A test's name, from its input.
+pub fn name(input: &Path) -> String {
+    input
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn load(assignment_dir: Option<&Path>, settings: &config::Settings) -> Vec<Check> {
+    let inputs = inputs(assignment_dir);
+
+    if inputs.is_empty() {
+        return vec![];
+    }
+
+    let Some(program) = &settings.test_command else {
+        println!(
+            "{} {} has tests but no test_command, skipping them",
+            "warning:".yellow(),
+            config::SETTINGS
+        );
+        return vec![];
+    };
+
+    inputs
+        .into_iter()
+        .map(|input| {
+            let name = name(&input);
+
+            Check {
+                points: settings.test_points.get(&name).copied().unwrap_or(1.0),
+                command: format!(
+                    "{} < {}{} {} -",
+                    program,
+                    quote(&input.to_string_lossy()),
+                    COMPARE,
+                    quote(&input.with_extension("out").to_string_lossy())
+                ),
+                name: format!("{PREFIX}{name}"),
+                kind: Kind::Pass,
+                mounts: input.parent().map(Path::to_path_buf).into_iter().collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_command_leaves_out_the_comparison() {
+        let test = Check {
+            points: 1.0,
+            name: format!("{PREFIX}1"),
+            command: format!("./prog -v < '/t/1.in'{COMPARE} '/t/1.out' -"),
+            kind: Kind::Pass,
+            mounts: vec![],
+        };
+        assert_eq!(program_command(&test), Some("./prog -v < '/t/1.in'"));
+
+        let other = Check {
+            name: "compiles".into(),
+            ..test
+        };
+        assert_eq!(program_command(&other), None);
+    }
+}
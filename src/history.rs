@@ -0,0 +1,134 @@
+//! Local record of the grades and comments given to students, kept as json
+//! lines so a shared file can be appended to by the whole team.
+
+use chrono::{DateTime, Local, Utc};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Scope {
+    /// only feedback given by this grader
+    Mine,
+    /// feedback given by anyone sharing the history file
+    Team,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub timestamp: DateTime<Utc>,
+    pub grader: String,
+    pub course_id: usize,
+    pub assignment_id: usize,
+    pub assignment_name: String,
+    pub user_id: usize,
+    pub score: Option<f64>,
+    pub comment: Option<String>,
+    /// the score on canvas before this one was posted, for reverting
+    #[serde(default)]
+    pub previous_score: Option<f64>,
+    /// the submission comment this posted on canvas, for reverting
+    #[serde(default)]
+    pub comment_id: Option<usize>,
+    /// the attempt graded, when not the latest
+    #[serde(default)]
+    pub attempt: Option<usize>,
+    /// posted as a provisional grade on a moderated assignment
+    #[serde(default)]
+    pub provisional: bool,
+}
+
+impl Record {
+    pub fn new(
+        course_id: usize,
+        assignment_id: usize,
+        assignment_name: &str,
+        user_id: usize,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            grader: grader_name(),
+            course_id,
+            assignment_id,
+            assignment_name: assignment_name.to_string(),
+            user_id,
+            score: None,
+            comment: None,
+            previous_score: None,
+            comment_id: None,
+            attempt: None,
+            provisional: false,
+        }
+    }
+
+    pub fn print(&self) {
+        println!(
+            "\t{} {} by {}{}",
+            self.timestamp.with_timezone(&Local).format("%Y-%m-%d"),
+            self.assignment_name.bright_blue(),
+            self.grader,
+            self.score.map(|s| format!(": {s}")).unwrap_or_default()
+        );
+
+        if let Some(comment) = &self.comment {
+            for line in comment.lines() {
+                println!("\t\t{}", line.dimmed());
+            }
+        }
+    }
+}
+
+fn path() -> PathBuf {
+    env::var("GRADER_HISTORY")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(".grader/history.jsonl"))
+}
+
+pub fn grader_name() -> String {
+    env::var("GRADER_NAME")
+        .or_else(|_| env::var("USER"))
+        .unwrap_or_else(|_| "unknown".into())
+}
+
+pub async fn load(scope: Scope) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+    let contents = match fs::read_to_string(path()).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+
+    let grader = grader_name();
+
+    Ok(contents
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(serde_json::from_str::<Record>)
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|r| matches!(scope, Scope::Team) || r.grader == grader)
+        .collect())
+}
+
+pub async fn append(record: &Record) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?
+        .write_all(line.as_bytes())
+        .await?;
+
+    Ok(())
+}
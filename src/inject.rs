@@ -0,0 +1,134 @@
+//! Instructor files (test harness, reference headers, a fixed Makefile)
+//! copied into each submission before the checks run. The assignment's
+//! `inject/` directory overwrites the student's copies, `defaults/` only fills
+//! in files the student did not submit.
+//!
+//! Everything injected or generated while grading is removed again when
+//! leaving the student, so the submission left on disk is only their work.
+
+use crate::config;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use walkdir::WalkDir;
+
+#[derive(Debug, Default)]
+pub struct Injected {
+    pub paths: Vec<PathBuf>,
+    /// the student's copies that were overwritten
+    originals: Vec<(PathBuf, Vec<u8>)>,
+}
+
+/// Copies the assignment's instructor files into `dir`.
+pub async fn inject(
+    assignment_dir: Option<&Path>,
+    dir: &Path,
+) -> Result<Injected, Box<dyn std::error::Error>> {
+    let mut injected = Injected::default();
+
+    let Some(assignment_dir) = assignment_dir else {
+        return Ok(injected);
+    };
+
+    for (source, overwrite) in [(config::INJECT, true), (config::DEFAULTS, false)] {
+        let source = assignment_dir.join(source);
+
+        if !source.is_dir() {
+            continue;
+        }
+
+        for entry in WalkDir::new(&source) {
+            let entry = entry?;
+
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let target = dir.join(entry.path().strip_prefix(&source)?);
+
+            if target.exists() {
+                if !overwrite {
+                    continue;
+                }
+
+                injected
+                    .originals
+                    .push((target.clone(), fs::read(&target).await?));
+            }
+
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+
+            fs::copy(entry.path(), &target).await?;
+            injected.paths.push(target);
+        }
+    }
+
+    Ok(injected)
+}
+
+pub fn print(injected: &Injected, dir: &Path) {
+    if injected.paths.is_empty() {
+        return;
+    }
+
+    let files: Vec<_> = injected
+        .paths
+        .iter()
+        .map(|p| p.strip_prefix(dir).unwrap_or(p).display().to_string())
+        .collect();
+
+    println!("Injected {}", files.join(", "));
+}
+
+/// The student's own files, taken before anything is injected or built.
+pub struct Snapshot {
+    dir: PathBuf,
+    entries: HashSet<PathBuf>,
+}
+
+fn walk(dir: &Path) -> impl Iterator<Item = walkdir::DirEntry> {
+    WalkDir::new(dir)
+        .min_depth(1)
+        .contents_first(true)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(Result::ok)
+}
+
+impl Snapshot {
+    pub fn take(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            entries: walk(dir).map(|e| e.into_path()).collect(),
+        }
+    }
+
+    /// Removes everything added since the snapshot and puts back the
+    /// student's copies of injected files, returning how many were removed.
+    pub async fn restore(&self, injected: &Injected) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut removed = 0;
+
+        // contents first, so directories are empty by the time they are seen
+        for entry in walk(&self.dir) {
+            if self.entries.contains(entry.path()) {
+                continue;
+            }
+
+            match entry.file_type().is_dir() {
+                true => fs::remove_dir(entry.path()).await?,
+                false => {
+                    fs::remove_file(entry.path()).await?;
+                    removed += 1;
+                }
+            }
+        }
+
+        for (path, contents) in &injected.originals {
+            fs::write(path, contents).await?;
+        }
+
+        Ok(removed)
+    }
+}
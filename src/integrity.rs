@@ -0,0 +1,429 @@
+//! Academic integrity signals computed over the whole cohort before anyone is
+//! graded.
+
+use crate::DownloadedSubmission;
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use colored::Colorize;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use walkdir::WalkDir;
+
+/// A file that is byte for byte identical to another student's.
+#[derive(Debug)]
+pub struct Duplicate {
+    pub file: String,
+    pub others: Vec<String>,
+}
+
+/// A submission from a previous semester's archive.
+#[derive(Debug)]
+pub struct ArchivedSubmission {
+    label: String,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+/// Hashes every non-empty file below `dir`, keyed by its path relative to
+/// `dir`.
+fn hash_dir(dir: &Path) -> Result<Vec<(String, Vec<u8>)>, Box<dyn std::error::Error>> {
+    let mut files = vec![];
+
+    for entry in WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+    {
+        let entry = entry?;
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let contents = std::fs::read(entry.path())?;
+
+        if contents.is_empty() {
+            continue;
+        }
+
+        let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+
+        files.push((
+            relative.display().to_string(),
+            Sha256::digest(&contents).to_vec(),
+        ));
+    }
+
+    Ok(files)
+}
+
+fn hash_zip(path: &Path) -> Result<Vec<(String, Vec<u8>)>, Box<dyn std::error::Error>> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    let mut files = vec![];
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+
+        if !file.is_file()
+            || file.size() == 0
+            || file.name().starts_with("__MACOSX/")
+            || file.name().contains(".git/")
+        {
+            continue;
+        }
+
+        let mut contents = vec![];
+        file.read_to_end(&mut contents)?;
+
+        files.push((file.name().to_string(), Sha256::digest(&contents).to_vec()));
+    }
+
+    Ok(files)
+}
+
+/// Loads every submission in the given archives. Each entry directly inside
+/// an archive directory is one submission, either a directory or a zip.
+pub fn load_archives(
+    archives: &[PathBuf],
+) -> Result<Vec<ArchivedSubmission>, Box<dyn std::error::Error>> {
+    let mut submissions = vec![];
+
+    for archive in archives {
+        let archive_name = archive
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        for entry in std::fs::read_dir(archive)? {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            let files = if path.is_dir() {
+                hash_dir(&path)?
+            } else if name.to_lowercase().ends_with(".zip") {
+                hash_zip(&path)?
+            } else {
+                continue;
+            };
+
+            submissions.push(ArchivedSubmission {
+                label: format!("{archive_name}/{name}"),
+                files,
+            });
+        }
+    }
+
+    Ok(submissions)
+}
+
+/// Finds files shared between students, or with an archived submission,
+/// ignoring anything that matches a file in the starter directory.
+pub fn find_duplicates(
+    submissions: &[DownloadedSubmission],
+    archived: &[ArchivedSubmission],
+    starter: Option<&Path>,
+) -> Result<HashMap<usize, Vec<Duplicate>>, Box<dyn std::error::Error>> {
+    let starter: HashSet<_> = match starter {
+        Some(dir) if dir.is_dir() => hash_dir(dir)?.into_iter().map(|(_, h)| h).collect(),
+        _ => HashSet::new(),
+    };
+
+    // (user id, description, file), archived submissions have no user id
+    let mut by_hash: HashMap<Vec<u8>, Vec<(Option<usize>, String, String)>> = HashMap::new();
+
+    for s in submissions {
+        for (file, hash) in hash_dir(&s.path)? {
+            if !starter.contains(&hash) {
+                by_hash.entry(hash).or_default().push((
+                    Some(s.user_id),
+                    format!("{} ({})", s.name, file),
+                    file,
+                ));
+            }
+        }
+    }
+
+    for s in archived {
+        for (file, hash) in &s.files {
+            // only worth keeping if a current student has it too
+            if let Some(owners) = by_hash.get_mut(hash) {
+                owners.push((
+                    None,
+                    format!("{} ({}, archived)", s.label, file),
+                    file.clone(),
+                ));
+            }
+        }
+    }
+
+    let mut duplicates: HashMap<usize, Vec<Duplicate>> = HashMap::new();
+
+    for owners in by_hash.values() {
+        for (user_id, _, file) in owners {
+            let Some(user_id) = user_id else {
+                continue;
+            };
+
+            let others: Vec<_> = owners
+                .iter()
+                .filter(|(o, _, _)| o != &Some(*user_id))
+                .map(|(_, other, _)| other.clone())
+                .collect();
+
+            if !others.is_empty() {
+                duplicates.entry(*user_id).or_default().push(Duplicate {
+                    file: file.clone(),
+                    others,
+                });
+            }
+        }
+    }
+
+    Ok(duplicates)
+}
+
+pub fn print_duplicates(duplicates: &[Duplicate]) {
+    for d in duplicates {
+        println!(
+            "\t{} {} identical to {}",
+            "✗".red(),
+            d.file,
+            d.others.join(", ")
+        );
+    }
+}
+
+/// Zip entries can be this far past the submission time before being flagged,
+/// since their timestamps are in the student's local time.
+const CLOCK_TOLERANCE: i64 = 12;
+
+/// Entries this many days older than the submission are flagged.
+const STALE_DAYS: i64 = 365;
+
+static CORE_AUTHOR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"<(?:dc:creator|cp:lastModifiedBy)>([^<]*)</(?:dc:creator|cp:lastModifiedBy)>")
+        .unwrap()
+});
+
+static PDF_AUTHOR: Lazy<regex::bytes::Regex> =
+    Lazy::new(|| regex::bytes::Regex::new(r"/Author\s*\(([^)]*)\)").unwrap());
+
+fn zip_time(time: zip::DateTime) -> Option<DateTime<Local>> {
+    NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?
+        .and_hms_opt(
+            time.hour().into(),
+            time.minute().into(),
+            time.second().into(),
+        )
+        .and_then(|t| Local.from_local_datetime(&t).earliest())
+}
+
+/// Flags entries modified after the submission or long before it.
+pub fn audit_timestamps<R: Read + Seek>(
+    archive: R,
+    submitted_at: Option<DateTime<Utc>>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut archive = zip::ZipArchive::new(archive)?;
+    let reference = submitted_at.unwrap_or_else(Utc::now).with_timezone(&Local);
+
+    let mut notes = vec![];
+
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+
+        if !file.is_file() {
+            continue;
+        }
+
+        let Some(modified) = zip_time(file.last_modified()) else {
+            continue;
+        };
+
+        if submitted_at.is_some() && modified > reference + Duration::hours(CLOCK_TOLERANCE) {
+            notes.push(format!(
+                "{} modified after submission ({})",
+                file.name(),
+                modified.format("%Y-%m-%d %H:%M")
+            ));
+        } else if modified < reference - Duration::days(STALE_DAYS) {
+            notes.push(format!(
+                "{} last modified {}",
+                file.name(),
+                modified.format("%Y-%m-%d")
+            ));
+        }
+    }
+
+    Ok(notes)
+}
+
+fn document_authors(path: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "docx" | "xlsx" | "pptx" => {
+            let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+            let mut core = String::new();
+
+            if let Ok(mut file) = archive.by_name("docProps/core.xml") {
+                file.read_to_string(&mut core)?;
+            }
+
+            Ok(CORE_AUTHOR
+                .captures_iter(&core)
+                .map(|c| c[1].trim().to_string())
+                .collect())
+        }
+        "pdf" => Ok(PDF_AUTHOR
+            .captures_iter(&std::fs::read(path)?)
+            .map(|c| String::from_utf8_lossy(&c[1]).trim().to_string())
+            .collect()),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Reports the author metadata of any documents in the submission, flagging
+/// authors that do not look like the student.
+pub fn audit_documents(
+    dir: &Path,
+    last_names: &[&str],
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut notes = vec![];
+    let last_names: Vec<_> = last_names.iter().map(|n| n.to_lowercase()).collect();
+
+    for entry in WalkDir::new(dir) {
+        let entry = entry?;
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let mut authors = document_authors(entry.path()).unwrap_or_default();
+        authors.retain(|a| !a.is_empty());
+        authors.dedup();
+
+        if authors.iter().any(|a| {
+            let a = a.to_lowercase();
+            !last_names.iter().any(|n| a.contains(n))
+        }) {
+            notes.push(format!(
+                "{} authored by {}",
+                entry
+                    .path()
+                    .strip_prefix(dir)
+                    .unwrap_or(entry.path())
+                    .display(),
+                authors.join(", ")
+            ));
+        }
+    }
+
+    Ok(notes)
+}
+
+pub fn print_notes(notes: &[String]) {
+    for note in notes {
+        println!("\t{} {}", "!".yellow(), note);
+    }
+}
+
+#[derive(Debug)]
+pub struct Commit {
+    hash: String,
+    author: String,
+    email: String,
+    date: String,
+    subject: String,
+}
+
+/// Reads the log of any git repository included in the submission.
+pub fn git_history(dir: &Path) -> Vec<Commit> {
+    let Some(repo) = WalkDir::new(dir)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(Result::ok)
+        .find(|e| e.file_type().is_dir() && e.file_name() == ".git")
+    else {
+        return vec![];
+    };
+
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(repo.path())
+        .args([
+            "log",
+            "--all",
+            "--date=short",
+            "--format=%h%x09%an%x09%ae%x09%ad%x09%s",
+        ])
+        .stderr(Stdio::null())
+        .output();
+
+    let Ok(output) = output else {
+        return vec![];
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, '\t');
+
+            Some(Commit {
+                hash: fields.next()?.to_string(),
+                author: fields.next()?.to_string(),
+                email: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Prints who committed to the repository and the most recent commits,
+/// highlighting authors that do not look like the student.
+pub fn print_git_history(commits: &[Commit], last_names: &[&str]) {
+    let last_names: Vec<_> = last_names.iter().map(|n| n.to_lowercase()).collect();
+
+    let mut authors: Vec<(&str, &str, usize)> = vec![];
+    for c in commits {
+        match authors
+            .iter_mut()
+            .find(|(name, email, _)| *name == c.author && *email == c.email)
+        {
+            Some((_, _, count)) => *count += 1,
+            None => authors.push((c.author.as_str(), c.email.as_str(), 1)),
+        }
+    }
+
+    println!("Git history ({} commits):", commits.len());
+
+    for (name, email, count) in &authors {
+        let marker = match last_names
+            .iter()
+            .any(|n| name.to_lowercase().contains(n) || email.to_lowercase().contains(n))
+        {
+            true => "✔".green(),
+            false => "!".yellow(),
+        };
+
+        println!("\t{} {} <{}> {} commits", marker, name, email, count);
+    }
+
+    for c in commits.iter().take(10) {
+        println!(
+            "\t\t{} {} {} {}",
+            c.hash.dimmed(),
+            c.date,
+            c.author,
+            c.subject
+        );
+    }
+}
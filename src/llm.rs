@@ -0,0 +1,118 @@
+//! Optional feedback drafting through an OpenAI compatible chat completions
+//! endpoint (llama.cpp, ollama and friends all speak it).
+//!
+//! This is off unless `GRADER_LLM_ENDPOINT` is set.
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+const SYSTEM_PROMPT: &str = "You are a teaching assistant writing feedback on a student's \
+programming assignment. Using the automated check results, the grader's notes and the changes \
+the student made to the starter code, write a short, specific and encouraging comment addressed \
+to the student. Do not invent problems that are not mentioned and do not assign a score.";
+
+/// Characters of the diff sent, so a large submission fits the model's
+/// context.
+const DIFF_LIMIT: usize = 12_000;
+
+pub struct Drafter {
+    http: reqwest::Client,
+    endpoint: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<Message<'a>>,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    content: String,
+}
+
+impl Drafter {
+    pub fn from_env() -> Option<Self> {
+        let endpoint = env::var("GRADER_LLM_ENDPOINT").ok()?;
+
+        Some(Self {
+            http: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            model: env::var("GRADER_LLM_MODEL").unwrap_or_else(|_| "default".into()),
+            api_key: env::var("GRADER_LLM_API_KEY").ok(),
+        })
+    }
+
+    /// `diff` is the student's changes to the starter code, if any.
+    pub async fn draft(
+        &self,
+        checks: &str,
+        notes: &str,
+        diff: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut prompt = format!("Check results:\n{checks}\n\nGrader notes:\n{notes}");
+
+        if let Some(diff) = diff.filter(|d| !d.trim().is_empty()) {
+            let end = diff
+                .char_indices()
+                .nth(DIFF_LIMIT)
+                .map_or(diff.len(), |(i, _)| i);
+
+            prompt.push_str(&format!(
+                "\n\nChanges to the starter code:\n{}",
+                &diff[..end]
+            ));
+            if end < diff.len() {
+                prompt.push_str("\n[diff truncated]");
+            }
+        }
+
+        let mut request = self
+            .http
+            .post(format!("{}/chat/completions", self.endpoint))
+            .json(&ChatRequest {
+                model: &self.model,
+                messages: vec![
+                    Message {
+                        role: "system",
+                        content: SYSTEM_PROMPT,
+                    },
+                    Message {
+                        role: "user",
+                        content: &prompt,
+                    },
+                ],
+            });
+
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response: ChatResponse = request.send().await?.error_for_status()?.json().await?;
+
+        Ok(response
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message.content)
+            .unwrap_or_default())
+    }
+}
@@ -0,0 +1,273 @@
+//! Offline grading of a directory of submissions, e.g. the zip from canvas'
+//! "Download Submissions" or an archive from a previous semester. Grades are
+//! written to a csv instead of being posted.
+
+use crate::due_date::Lateness;
+use crate::{
+    canvas, checks, config, deductions, extract, file_checks, flag_duplicates, guard, integrity,
+    last_name, roster, run_checks, scripts, snippets, DownloadedSubmission, Graded, Options,
+    Session,
+};
+use dialoguer::{theme::ColorfulTheme, MultiSelect};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// `lastfirst_[LATE_]userid_attachmentid_filename` as named by canvas
+static CANVAS_NAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^([^_]+)_(late_)?(\d+)_\d+_(.+)$").unwrap());
+
+#[derive(Debug, Default)]
+struct LocalStudent {
+    user_id: Option<usize>,
+    late: bool,
+    /// already extracted directory to grade in place
+    dir: Option<PathBuf>,
+    /// files to extract or copy, with their original names
+    files: Vec<(PathBuf, String)>,
+}
+
+#[derive(Serialize)]
+struct Row<'a> {
+    student: &'a str,
+    user_id: Option<usize>,
+    score: Option<f64>,
+    comment: Option<&'a str>,
+    note: Option<&'a str>,
+}
+
+async fn scan(dir: &Path) -> Result<BTreeMap<String, LocalStudent>, Box<dyn std::error::Error>> {
+    let mut students: BTreeMap<String, LocalStudent> = BTreeMap::new();
+    let mut entries = fs::read_dir(dir).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+
+        if entry.file_type().await?.is_dir() {
+            students.entry(file_name).or_default().dir = Some(entry.path());
+            continue;
+        }
+
+        match CANVAS_NAME.captures(&file_name) {
+            Some(caps) => {
+                let student = students.entry(caps[1].to_string()).or_default();
+                student.user_id = caps[3].parse().ok();
+                student.late |= caps.get(2).is_some();
+                student.files.push((entry.path(), caps[4].to_string()));
+            }
+            None => {
+                let name = Path::new(&file_name)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| file_name.clone());
+
+                students
+                    .entry(name)
+                    .or_default()
+                    .files
+                    .push((entry.path(), file_name));
+            }
+        }
+    }
+
+    Ok(students)
+}
+
+/// Extracts or copies the student's files, returning the directory to grade
+/// and any integrity notes. Document authors are only checked against a
+/// name from the roster, since bulk download names are squashed together.
+async fn prepare(
+    name: &str,
+    roster_name: Option<&str>,
+    student: &LocalStudent,
+) -> Result<(PathBuf, Vec<String>), Box<dyn std::error::Error>> {
+    let mut notes = vec![];
+
+    let path = match &student.dir {
+        Some(dir) => dir.clone(),
+        None => {
+            let path = PathBuf::from(name);
+            fs::create_dir_all(&path).await?;
+
+            for (source, original) in &student.files {
+                if original.to_lowercase().ends_with(".zip") {
+                    notes.extend(integrity::audit_timestamps(
+                        std::fs::File::open(source)?,
+                        None,
+                    )?);
+                    extract(std::fs::File::open(source)?, &path).await?;
+                } else {
+                    fs::copy(source, path.join(original)).await?;
+                }
+            }
+
+            path
+        }
+    };
+
+    if let Some(roster_name) = roster_name {
+        notes.extend(integrity::audit_documents(
+            &path,
+            &[last_name(roster_name)],
+        )?);
+    }
+
+    Ok((path, notes))
+}
+
+pub async fn run(
+    dir: &Path,
+    assignment: Option<&str>,
+    points_possible: Option<f64>,
+    output: &Path,
+    roster: roster::Roster,
+    archives: &[PathBuf],
+    options: Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let students = scan(dir).await?;
+    let names: Vec<_> = students.keys().collect();
+
+    let selections = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Users to grade")
+        .items(&names)
+        .defaults(&vec![true; names.len()])
+        .interact()?;
+
+    let assignment_dir = assignment.and_then(|name| config::assignment_dir(None, name));
+
+    // ids only need to tell students apart when there is none in the file
+    // name, so they start past the real ones
+    let unused = students
+        .values()
+        .filter_map(|s| s.user_id)
+        .max()
+        .map_or(0, |id| id + 1);
+    let ids: Vec<_> = students
+        .values()
+        .enumerate()
+        .map(|(i, s)| s.user_id.unwrap_or(unused + i))
+        .collect();
+
+    let users: Vec<_> = names
+        .iter()
+        .zip(&ids)
+        .map(|(name, &id)| {
+            let entry = roster.find(id, name).cloned().unwrap_or_default();
+
+            canvas::User {
+                id,
+                name: name.to_string(),
+                sortable_name: entry.name.unwrap_or_else(|| name.to_string()),
+                login_id: entry.login_id,
+                email: entry.email,
+                sis_user_id: None,
+            }
+        })
+        .collect();
+
+    let mut session = Session {
+        history: vec![],
+        guard: guard::Guard::new(&users).await?,
+        students: users.into_iter().map(|u| (u.id, u)).collect(),
+        template: config::read(assignment_dir.as_deref(), config::COMMENT_TEMPLATE).await,
+        deductions: config::read(assignment_dir.as_deref(), config::DEDUCTIONS)
+            .await
+            .map(|d| deductions::parse(&d))
+            .unwrap_or_default(),
+        snippets: config::read(assignment_dir.as_deref(), config::SNIPPETS)
+            .await
+            .map(|s| snippets::parse(&s))
+            .unwrap_or_default(),
+        settings: config::settings(assignment_dir.as_deref()).await,
+        scripts: assignment_dir
+            .as_deref()
+            .map(|d| scripts::load(&d.join(config::SCRIPTS)))
+            .unwrap_or_default(),
+        rubric: vec![],
+        checks: checks::load(assignment_dir.as_deref(), options.build_by_default).await,
+        check_results: HashMap::new(),
+        late_policy: None,
+        points_possible,
+        roster,
+        duplicates: HashMap::new(),
+        similar: HashMap::new(),
+        assignment_dir,
+        speed_grader: None,
+        existing: HashMap::new(),
+        graders: HashMap::new(),
+        moderated: false,
+        peer_reviews: HashMap::new(),
+        record: options.record,
+        sandbox: options.sandbox,
+    };
+
+    let mut downloaded = vec![];
+
+    for &s in &selections {
+        let name = names[s];
+        let student = &students[name];
+
+        // bulk download names are squashed together, prefer the roster's
+        let roster_name = session
+            .roster
+            .find(ids[s], name)
+            .and_then(|e| e.name.clone());
+        let display_name = roster_name.clone().unwrap_or_else(|| name.clone());
+
+        let (path, notes) = prepare(&display_name, roster_name.as_deref(), student).await?;
+
+        let d = DownloadedSubmission {
+            user_id: ids[s],
+            path,
+            notes,
+            name: display_name,
+            lateness: match student.late {
+                true => Lateness::Marked,
+                false => Lateness::NoDueDate,
+            },
+            due_at: None,
+            attempt: None,
+            group: None,
+        };
+
+        downloaded.push(d);
+    }
+
+    flag_duplicates(&mut session, &downloaded, archives)?;
+    file_checks::print_missing(&session.settings, &downloaded);
+    run_checks(&mut session, &downloaded).await?;
+
+    let mut writer = csv::Writer::from_path(output)?;
+
+    let mut queue: VecDeque<_> = selections.into_iter().zip(&downloaded).collect();
+
+    while let Some((s, d)) = queue.pop_front() {
+        let outcome = match d.grade(&session).await? {
+            Graded::Done(outcome) => outcome,
+            Graded::Deferred => {
+                println!("{} moved to the end of the queue", d.name);
+                queue.push_back((s, d));
+                continue;
+            }
+            Graded::Exit => break,
+        };
+
+        writer.serialize(Row {
+            student: names[s],
+            user_id: students[names[s]].user_id,
+            score: outcome.score,
+            comment: outcome.comment.as_deref(),
+            note: outcome.note.as_deref(),
+        })?;
+        writer.flush()?;
+    }
+
+    println!("Grades written to {}", output.display());
+
+    Ok(())
+}
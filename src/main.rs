@@ -1,65 +1,683 @@
+mod annotate;
+mod attachments;
+mod banned;
+mod bench;
+mod browser;
+mod cache;
+mod canvas;
+mod checks;
+mod claims;
+mod config;
+mod crash;
+mod debug;
+mod deductions;
+mod diff;
+mod dossier;
+mod due_date;
+mod export;
+mod file_checks;
+mod gradescope;
+mod graphql;
+mod groups;
+mod guard;
+mod harness;
+mod history;
+mod inject;
+mod integrity;
+mod llm;
+mod local;
+mod memcheck;
+mod memory;
+mod moss;
+mod oauth;
+mod office_hours;
+mod peer_eval;
+mod post_policy;
+mod profiles;
+mod record;
+mod repo;
+mod resume;
+mod retry;
+mod revert;
+mod roster;
+mod rubric;
+mod sandbox;
+mod scripts;
+mod secrets;
+mod similarity;
+mod snippets;
+mod spell;
+mod staff;
+mod style;
+mod template;
+mod throttle;
+mod watch;
+
+use canvasapi::models::assignment::Assignment;
 use canvasapi::models::user::UserProfile;
-use canvasapi::prelude::{Canvas, CanvasInformation, Submission};
+use canvasapi::prelude::{CanvasInformation, Submission};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
 use colored::Colorize;
-use dialoguer::{theme::ColorfulTheme, FuzzySelect, Input, MultiSelect, Confirm};
+use console::{Key, Term};
+use dialoguer::{theme::ColorfulTheme, Confirm, FuzzySelect, Input, MultiSelect, Select};
 use dotenv::dotenv;
+use due_date::{DueDates, Lateness};
 use futures::prelude::*;
-use futures::stream::FuturesOrdered;
-use once_cell::unsync::Lazy;
 use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::env;
-use std::path::PathBuf;
-use std::process::{Command, Stdio, exit};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use strum::IntoEnumIterator;
 use tokio::fs;
 
-const README_DISCLAIMER: &str =
-    "by submitting this file to carmen, i certify that i have performed all";
+/// User profiles fetched at once, so canvas does not throttle us.
+const PROFILE_REQUESTS: usize = 8;
+
+/// Subdirectory of the student's directory files attached to submission
+/// comments are saved to.
+const COMMENT_FILES: &str = "comments";
+
+/// Starting text of the comment recorded with a zero for no submission.
+const MISSING_COMMENT: &str = "No submission was received for this assignment.";
+
+/// Where online text entry submissions are written, checked like a readme.
+const TEXT_ENTRY: &str = "submission.txt";
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Show feedback previously given to each student
+    #[arg(long, value_enum)]
+    history: Option<history::Scope>,
+
+    /// Roster csv with preferred names, usernames and sections
+    #[arg(long)]
+    roster: Option<PathBuf>,
+
+    /// Directory of previous semesters' submissions to check for identical
+    /// files, may be repeated
+    #[arg(long)]
+    archive: Vec<PathBuf>,
+
+    /// Only list courses in the current term
+    #[arg(long)]
+    current_term: bool,
+
+    /// Only list courses starred in canvas
+    #[arg(long)]
+    favorites: bool,
+
+    /// Order of the assignment list
+    #[arg(long, value_enum)]
+    sort: Option<AssignmentOrder>,
+
+    /// Only list published assignments
+    #[arg(long)]
+    published: bool,
+
+    /// Only list assignments that are past due
+    #[arg(long)]
+    past_due: bool,
+
+    /// Only list assignments due on or after this date, e.g. 2024-09-01
+    #[arg(long)]
+    due_after: Option<NaiveDate>,
+
+    /// Only list assignments due on or before this date
+    #[arg(long)]
+    due_before: Option<NaiveDate>,
+
+    /// Ask which attempt to grade for students who submitted more than once
+    #[arg(long)]
+    attempts: bool,
+
+    /// Only grade submissions waiting to be graded, e.g. when resuming after
+    /// grading some in SpeedGrader
+    #[arg(long)]
+    ungraded: bool,
+
+    /// Record the shell, editors and gdb while grading each student to an
+    /// asciinema cast next to their feedback
+    #[arg(long)]
+    record: bool,
+
+    /// Also download files students attached to submission comments, e.g.
+    /// corrections sent after the deadline
+    #[arg(long)]
+    comment_files: bool,
+
+    /// Keep the grades posted hidden from students until every one is done,
+    /// then release them together
+    #[arg(long)]
+    hold_grades: bool,
+
+    /// Also list submissions excused from the assignment
+    #[arg(long)]
+    excused: bool,
+
+    /// Build submissions with make when the assignment has no build.txt,
+    /// showing the errors and warnings
+    #[arg(long)]
+    build: bool,
+
+    /// Run checks in a sandbox where only the student's directory is
+    /// writable
+    #[arg(long, value_enum)]
+    sandbox: Option<sandbox::Backend>,
+
+    /// Submit the portion's C files to MOSS once they are downloaded and
+    /// print the results url
+    #[arg(long)]
+    moss: bool,
+
+    /// Split the submissions into portions alphabetically, as before, rather
+    /// than by a hash of each student's id that doesn't change as
+    /// submissions come in
+    #[arg(long)]
+    contiguous: bool,
+
+    /// Staff csv assigning students to TAs, picked from instead of dividing
+    /// into portions
+    #[arg(long)]
+    staff: Option<PathBuf>,
+
+    /// Shared directory where graders claim students, so those claimed by
+    /// another grader are left out
+    #[arg(long)]
+    claims: Option<PathBuf>,
+
+    /// Regrade one student, picked by name from every submission, without
+    /// dividing into portions or touching the saved session
+    #[arg(long)]
+    regrade: bool,
+
+    /// Include canvas's Test Student, e.g. for a dry run
+    #[arg(long)]
+    test_student: bool,
+
+    /// Canvas profile to use from the profiles file
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Fetch courses, assignments and submissions from canvas instead of the
+    /// cache
+    #[arg(long)]
+    refresh: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum AssignmentOrder {
+    /// Soonest due first, undated last
+    Due,
+    Name,
+    /// Most submissions waiting first
+    NeedsGrading,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Adjust a group project grade per member from a peer evaluation csv
+    PeerEval { csv: PathBuf },
+    /// Grade a local directory of submissions without canvas
+    Local {
+        /// Directory of submission archives, e.g. from "Download Submissions"
+        dir: PathBuf,
+        /// Assignment name used to find the assignment configuration
+        #[arg(long)]
+        assignment: Option<String>,
+        /// Points possible for the assignment
+        #[arg(long)]
+        points: Option<f64>,
+        /// Where to write the grades
+        #[arg(long, default_value = "grades.csv")]
+        output: PathBuf,
+    },
+    /// Gather one student's submissions to several assignments in one place
+    Dossier {
+        /// Part of the student's name, login or email
+        student: String,
+    },
+    /// Download one student's submission to the assignment, e.g. when they
+    /// come to office hours
+    Student {
+        /// Part of the student's name, login or email
+        name: String,
+        /// Re-run the checks whenever a file changes instead of opening a
+        /// shell
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Run the assignment's checks as a gradescope autograder
+    Gradescope {
+        /// The submission to check
+        #[arg(default_value = "/autograder/submission")]
+        dir: PathBuf,
+        /// Assignment name used to find the assignment configuration
+        #[arg(long)]
+        assignment: Option<String>,
+        /// Where to write the results
+        #[arg(long, default_value = "/autograder/results/results.json")]
+        output: PathBuf,
+        /// When students see the results
+        #[arg(long, value_enum, default_value_t)]
+        visibility: gradescope::Visibility,
+    },
+    /// Show what changed between two of a student's attempts at the
+    /// assignment
+    Diff {
+        /// Part of the student's name, login or email
+        student: String,
+    },
+    /// Restore the score a grade posted to the assignment replaced and delete
+    /// its comment, by default the last one posted
+    Revert {
+        /// Part of the student's name, login or email
+        student: Option<String>,
+    },
+    /// Save the canvas access token in the system keyring instead of .env
+    SaveToken,
+    /// Release the assignment's hidden grades to students
+    PostGrades,
+    /// Write the scores recorded in the last grading session as a canvas
+    /// gradebook import csv, and the comments to a csv beside it
+    Export {
+        #[arg(default_value = "gradebook.csv")]
+        output: PathBuf,
+    },
+}
+
+/// Order students are graded in within an assignment. Portions are divided
+/// first so they do not depend on who picks which order.
+#[derive(Clone, Copy, strum::Display, strum::EnumIter)]
+enum QueueOrder {
+    #[strum(serialize = "Alphabetical")]
+    Alphabetical,
+    #[strum(serialize = "Submission time, earliest first")]
+    SubmissionTime,
+    #[strum(serialize = "Previous assignment score, lowest first")]
+    PreviousScore,
+    #[strum(serialize = "Lateness, latest first")]
+    Lateness,
+    /// shuffled with the session's seed, so resuming keeps the order
+    #[strum(serialize = "Random")]
+    Random,
+}
+
+/// What to do once a student's submission has been looked over.
+#[derive(Clone, Copy, strum::Display, strum::EnumIter)]
+enum Next {
+    Grade,
+    #[strum(serialize = "Come back later")]
+    Defer,
+    Exit,
+}
+
+/// Which submissions are divided up and graded.
+#[derive(Clone, Copy, PartialEq, strum::Display, strum::EnumIter)]
+enum StatusFilter {
+    #[strum(serialize = "On time")]
+    OnTime,
+    Late,
+    Missing,
+    #[strum(serialize = "Already graded")]
+    Graded,
+}
+
+/// Where the comment posted with a grade comes from.
+#[derive(Clone, Copy, PartialEq, strum::Display, strum::EnumIter)]
+enum CommentSource {
+    #[strum(serialize = "Post the feedback written while grading")]
+    Feedback,
+    #[strum(serialize = "Type a comment")]
+    Typed,
+    #[strum(serialize = "Insert canned comments")]
+    Snippets,
+    #[strum(serialize = "Load a comment from a file")]
+    File,
+    #[strum(serialize = "No comment")]
+    None,
+}
+
+/// How the command line asks for students to be graded, in every mode.
+#[derive(Debug, Clone, Copy)]
+struct Options {
+    /// record the interactive part of grading with asciinema
+    record: bool,
+    /// build with `make` when the assignment has no `build.txt`
+    build_by_default: bool,
+    /// where checks run
+    sandbox: sandbox::Backend,
+}
+
+/// State shared by every student graded in this run.
+struct Session {
+    history: Vec<history::Record>,
+    guard: guard::Guard,
+    template: Option<String>,
+    deductions: Vec<deductions::Deduction>,
+    snippets: Vec<snippets::Snippet>,
+    settings: config::Settings,
+    scripts: Vec<scripts::Script>,
+    rubric: Vec<canvas::Criterion>,
+    checks: Vec<checks::Check>,
+    check_results: HashMap<usize, Vec<checks::CheckResult>>,
+    late_policy: Option<canvas::LatePolicy>,
+    points_possible: Option<f64>,
+    roster: roster::Roster,
+    students: HashMap<usize, canvas::User>,
+    duplicates: HashMap<usize, Vec<integrity::Duplicate>>,
+    similar: HashMap<usize, Vec<similarity::Similar>>,
+    assignment_dir: Option<PathBuf>,
+    /// SpeedGrader for the assignment, when grading from canvas
+    speed_grader: Option<String>,
+    /// grades and comments already on canvas, by user id
+    existing: HashMap<usize, canvas::SubmissionGrade>,
+    /// names of the teachers and TAs, by user id
+    graders: HashMap<usize, String>,
+    /// scores are posted as provisional grades for the moderator
+    moderated: bool,
+    /// peer reviews of each student's submission, by user id
+    peer_reviews: HashMap<usize, Vec<canvas::PeerReview>>,
+    /// the interactive part of grading is recorded with asciinema
+    record: bool,
+    /// where checks run
+    sandbox: sandbox::Backend,
+}
+
+impl Session {
+    fn is_excused(&self, user_id: usize) -> bool {
+        self.existing.get(&user_id).is_some_and(|e| e.is_excused())
+    }
+}
+
+/// What the grader decided for a student.
+#[derive(Debug, Default)]
+struct Grade<'a> {
+    score: Option<f64>,
+    deductions: Vec<&'a deductions::Deduction>,
+    rubric: Option<rubric::RubricAssessment<'a>>,
+}
+
+/// The result of grading a student, once the comment has been written.
+#[derive(Debug)]
+struct Outcome<'a> {
+    score: Option<f64>,
+    comment: Option<String>,
+    rubric: Option<rubric::RubricAssessment<'a>>,
+    /// annotated copies of the student's files to upload with the comment
+    annotations: Vec<PathBuf>,
+    /// the grader's own note, kept in the session file
+    note: Option<String>,
+}
+
+/// What came of bringing a student up for grading.
+#[derive(Debug)]
+enum Graded<'a> {
+    Done(Outcome<'a>),
+    /// to come back to once the others are graded
+    Deferred,
+    /// the grader stopped for now
+    Exit,
+}
 
 #[derive(Debug, strum::Display)]
 enum Errors {
     AttachmentNotFound,
+    CloneFailed,
     InvalidSelection,
+    NoCommitBeforeSubmission,
+    StudentNotFound,
 }
 
 impl std::error::Error for Errors {}
 
 #[derive(Debug)]
 struct UserSubmission {
-    user_profile: UserProfile,
+    user_id: usize,
+    /// "Last, First", empty when canvas has none
+    sortable_name: String,
     submission: Submission,
+    due_at: Option<DateTime<Utc>>,
+    group: Option<groups::GroupSubmission>,
+    /// an earlier attempt to grade instead of the latest
+    attempt: Option<canvas::Attempt>,
 }
 
 impl UserSubmission {
-    async fn download_submission(self) -> Result<DownloadedSubmission, Box<dyn std::error::Error>> {
-        let attachment = &self
-            .submission
-            .attachments
-            .as_ref()
-            .ok_or(Errors::AttachmentNotFound)?[0];
+    /// Sortable name, falling back to the roster when the profile has none,
+    /// or the group's name.
+    fn display_name(&self, roster: &roster::Roster) -> String {
+        if let Some(group) = &self.group {
+            return group.name.clone();
+        }
+
+        match self.sortable_name.trim() {
+            "" => roster
+                .find(self.user_id, "")
+                .and_then(|e| e.name.clone().or_else(|| e.login_id.clone()))
+                .unwrap_or_else(|| self.user_id.to_string()),
+            name => name.to_string(),
+        }
+    }
+
+    fn submitted_at(&self) -> Option<DateTime<Utc>> {
+        if let Some(attempt) = &self.attempt {
+            return attempt.submitted_at;
+        }
+
+        self.submission
+            .submitted_at
+            .as_deref()
+            .and_then(|s| s.parse().ok())
+    }
+
+    /// Against the student's own due date, including overrides.
+    fn lateness(&self) -> Lateness {
+        Lateness::new(self.submitted_at(), self.due_at)
+    }
+
+    async fn download_submission(
+        self,
+        client: &canvas::Client,
+        course_id: usize,
+        assignment_id: usize,
+        name: String,
+        path: PathBuf,
+    ) -> Result<DownloadedSubmission, Box<dyn std::error::Error>> {
+        let submitted_at = self.submitted_at();
+
+        // the chosen attempt, otherwise the latest
+        let (mut attachments, url, body): (Vec<_>, _, _) = match &self.attempt {
+            Some(a) => (
+                a.attachments
+                    .iter()
+                    .map(|a| (a.display_name.clone(), a.url.clone()))
+                    .collect(),
+                a.url.clone(),
+                a.body.clone(),
+            ),
+            None => (
+                self.submission
+                    .attachments
+                    .iter()
+                    .flatten()
+                    .map(|a| (a.display_name.clone(), a.url.clone()))
+                    .collect(),
+                self.submission.url.clone(),
+                self.submission.body.clone(),
+            ),
+        };
+
+        // a quiz's body is only canvas's summary of it, "user: 1, quiz: 2,
+        // score: ...", and the files are uploaded to its questions
+        if attachments.is_empty() && body.as_deref().is_some_and(|b| b.starts_with("user: ")) {
+            attachments = client
+                .get_quiz_uploads(
+                    course_id,
+                    assignment_id,
+                    self.user_id,
+                    self.attempt.as_ref().and_then(|a| a.attempt),
+                )
+                .await?
+                .into_iter()
+                .map(|a| (a.display_name, a.url))
+                .collect();
+        }
+
+        let mut notes = match (attachments, url, body) {
+            (attachments, _, _) if !attachments.is_empty() => {
+                let source = attachments::Source {
+                    client,
+                    course_id,
+                    assignment_id,
+                    user_id: self.user_id,
+                    attempt: self.attempt.as_ref().and_then(|a| a.attempt),
+                };
+
+                attachments::download(attachments, &path, submitted_at, &source).await?
+            }
+            // repository url submissions
+            (_, Some(url), _) => {
+                let cutoff = repo::cutoff(submitted_at, self.due_at);
+                let commit = repo::clone_at(&url, &path, cutoff).await?;
+                println!("Cloned {} at {}", url, &commit[..commit.len().min(10)]);
+
+                vec![]
+            }
+            // online text entry submissions
+            (_, _, Some(body)) => {
+                fs::create_dir_all(&path).await?;
+                fs::write(path.join(TEXT_ENTRY), html_to_text(&body)).await?;
 
-        let resp = reqwest::get(&attachment.url).await?;
-        let body = std::io::Cursor::new(resp.bytes().await?);
+                vec![]
+            }
+            _ => return Err(Errors::AttachmentNotFound.into()),
+        };
 
-        let path = PathBuf::from(&self.user_profile.sortable_name);
-        let path_move = path.clone();
-        tokio::task::spawn_blocking(move || zip_extract::extract(body, &path_move, true)).await??;
+        notes.extend(integrity::audit_documents(
+            &path,
+            &last_names(&name, self.group.as_ref()),
+        )?);
 
         Ok(DownloadedSubmission {
-            user_profile: self.user_profile,
+            user_id: self.user_id,
+            name,
             path,
+            lateness: self.lateness(),
+            due_at: self.due_at,
+            notes,
+            attempt: self.attempt.and_then(|a| a.attempt),
+            group: self.group,
         })
     }
 }
 
 #[derive(Debug)]
 struct DownloadedSubmission {
-    user_profile: UserProfile,
+    user_id: usize,
+    /// sortable name, "Last, First"
+    name: String,
     path: PathBuf,
+    lateness: Lateness,
+    due_at: Option<DateTime<Utc>>,
+    /// integrity notes from auditing the submitted files
+    notes: Vec<String>,
+    /// the attempt graded, when one was chosen
+    attempt: Option<usize>,
+    group: Option<groups::GroupSubmission>,
 }
 
 impl DownloadedSubmission {
-    async fn grade(&self) -> Result<(), Box<dyn std::error::Error>> {
-        println!("Grading {}", self.user_profile.sortable_name.bright_blue());
+    /// The user ids graded with the submission, every member of a group.
+    fn members(&self) -> Vec<usize> {
+        match &self.group {
+            Some(group) => group.members.clone(),
+            None => vec![self.user_id],
+        }
+    }
+
+    async fn grade<'a>(
+        &self,
+        session: &'a Session,
+    ) -> Result<Graded<'a>, Box<dyn std::error::Error>> {
+        match session.roster.find(self.user_id, &self.name) {
+            Some(entry) => println!("Grading {} ({})", self.name.bright_blue(), entry.describe()),
+            None => println!("Grading {}", self.name.bright_blue()),
+        }
+
+        match self.due_at {
+            Some(due_at) => println!(
+                "Submission is {} (due {})",
+                self.lateness,
+                due_at.with_timezone(&Local).format("%a %b %e %H:%M")
+            ),
+            None => println!("Submission is {}", self.lateness),
+        }
+
+        if let Some(attempt) = self.attempt {
+            println!("Grading attempt {}", attempt);
+        }
+
+        if let Some(group) = &self.group {
+            let members: Vec<_> = group
+                .members
+                .iter()
+                .map(|id| {
+                    session
+                        .students
+                        .get(id)
+                        .map(|s| s.sortable_name.clone())
+                        .unwrap_or_else(|| id.to_string())
+                })
+                .collect();
+
+            println!("Group members: {}", members.join("; "));
+        }
+
+        let prior: Vec<_> = session
+            .history
+            .iter()
+            .filter(|r| r.user_id == self.user_id)
+            .collect();
+
+        if !prior.is_empty() {
+            println!("Previous feedback:");
+            prior.iter().for_each(|r| r.print());
+        }
+
+        // so a regrade or a contradicting comment is a choice, not an accident
+        if let Some(existing) = session.existing.get(&self.user_id) {
+            print_existing(existing, &session.graders);
+        }
+
+        if let Some(reviews) = session.peer_reviews.get(&self.user_id) {
+            print_peer_reviews(reviews);
+        }
+
+        if let Some(duplicates) = session.duplicates.get(&self.user_id) {
+            println!("Identical to another submission:");
+            integrity::print_duplicates(duplicates);
+        }
+
+        if let Some(similar) = session.similar.get(&self.user_id) {
+            println!("Similar to another submission:");
+            similarity::print(similar);
+        }
+
+        if !self.notes.is_empty() {
+            println!("Integrity notes:");
+            integrity::print_notes(&self.notes);
+        }
+
+        let commits = integrity::git_history(&self.path);
+        if !commits.is_empty() {
+            integrity::print_git_history(&commits, &last_names(&self.name, self.group.as_ref()));
+        }
 
         let mut entries = fs::read_dir(&self.path).await?;
 
@@ -75,73 +693,361 @@ impl DownloadedSubmission {
             }
         }
 
-        let lower_case_name = self.user_profile.sortable_name.to_lowercase();
-        let last_name = lower_case_name.split(",").next().unwrap();
+        // any member of a group may have written their name
+        let mut names = vec![];
+        for user_id in self.members() {
+            let student = session.students.get(&user_id);
+            let name = match (&self.group, student) {
+                (None, _) => self.name.clone(),
+                (Some(_), Some(student)) => student.sortable_name.clone(),
+                (Some(_), None) => String::new(),
+            };
+
+            names.push(last_name(&name).to_lowercase());
+            names.extend(
+                session
+                    .roster
+                    .find(user_id, &name)
+                    .map(roster::Entry::aliases)
+                    .unwrap_or_default(),
+            );
+
+            // half the class writes their name.# or email instead of their name
+            if let Some(student) = student {
+                names.extend(student.login_id.as_deref().map(str::to_lowercase));
+                names.extend(
+                    student
+                        .email
+                        .as_deref()
+                        .and_then(|e| e.split('@').next())
+                        .map(str::to_lowercase),
+                );
+            }
+        }
+
+        names.retain(|n| !n.is_empty());
+
+        let re = file_checks::files_of_interest(&session.settings);
+
+        // outcome of each check, used for templates and feedback drafts
+        let mut summary = vec![];
+
+        for check in file_checks::registry(session, self, names) {
+            let result = check.run(&files);
+
+            result.print();
+            summary.extend(result.summary());
+        }
+
+        // the student's own files, before anything is injected or built
+        let snapshot = inject::Snapshot::take(&self.path);
+
+        let injected = inject::inject(session.assignment_dir.as_deref(), &self.path).await?;
+        inject::print(&injected, &self.path);
+
+        // already run in the batch phase unless it was skipped
+        let results = match session.check_results.get(&self.user_id) {
+            Some(results) => results.clone(),
+            None => checks::run_all(&session.checks, &self.path, session.sandbox).await?,
+        };
+
+        let recording = session
+            .record
+            .then(|| record::start(&self.path, &self.name));
+
+        if !results.is_empty() {
+            checks::print(&results);
+            summary.extend(results.iter().map(|r| (r.name.clone(), r.passed)));
+            debug::offer(
+                &session.checks,
+                &results,
+                &self.path,
+                session.sandbox,
+                recording.as_ref(),
+            )
+            .await?;
+        }
+
+        // to check annotations or the rubric history before grading
+        if let Some(url) = &session.speed_grader {
+            println!("Press o to open the submission in SpeedGrader, any other key to continue");
 
-        let re: Lazy<Regex> =
-            Lazy::new(|| Regex::new(r"(readme)|(\.c)|(\.h)|(makefile)|(.h)").unwrap());
+            if let Key::Char('o') = Term::stdout().read_key()? {
+                browser::open(&format!("{url}&student_id={}", self.user_id));
+            }
+        }
 
-        println!("File contains name:");
+        match query_next()? {
+            Next::Grade => {}
+            Next::Defer => {
+                snapshot.restore(&injected).await?;
+                return Ok(Graded::Deferred);
+            }
+            Next::Exit => {
+                snapshot.restore(&injected).await?;
+                return Ok(Graded::Exit);
+            }
+        }
 
         files
             .iter()
             .filter(|f| re.is_match(&f.name.to_lowercase()))
-            .for_each(|f| {
-                let contains = match f
-                    .contents
-                    .clone()
-                    .unwrap_or_default()
-                    .to_lowercase()
-                    .contains(&last_name)
-                {
-                    true => "✔".green(),
-                    false => "✗".red(),
-                };
+            .map(|f| f.open_file_in_editor(recording.as_ref()))
+            .collect::<Result<_, _>>()?;
 
-                println!("\t{} {}", contains, f.name);
-            });
+        open_shell(&self.path, recording.as_ref())?;
 
-        println!("File contains readme disclaimer:");
+        // only canvas takes files with the comment
+        let annotations = match session.speed_grader {
+            Some(_) => {
+                let sources: Vec<_> = files
+                    .iter()
+                    .filter(|f| re.is_match(&f.name.to_lowercase()))
+                    .map(|f| f.path.clone())
+                    .collect();
 
-        files
-            .iter()
-            .filter(|f| f.name.to_lowercase().contains("readme"))
-            .for_each(|f| {
-                let contains = match f
-                    .contents
-                    .clone()
-                    .unwrap_or_default()
-                    .to_lowercase()
-                    .contains(README_DISCLAIMER)
-                {
-                    true => "✔".green(),
-                    false => "✗".red(),
+                annotate::offer(&self.path, &sources, recording.as_ref()).await?
+            }
+            None => vec![],
+        };
+
+        let mut grade = Grade::default();
+
+        if !session.rubric.is_empty() {
+            let assessment = rubric::assess(&session.rubric)?;
+            grade.score = Some(assessment.score());
+            grade.rubric = Some(assessment);
+        } else if !session.deductions.is_empty() {
+            grade.deductions = deductions::select(&session.deductions, session.points_possible)?;
+            grade.score = session
+                .points_possible
+                .map(|p| deductions::score(&grade.deductions, p));
+        } else {
+            grade.score = read_score(session.points_possible)?;
+        }
+
+        let note = Input::<String>::with_theme(&ColorfulTheme::default())
+            .with_prompt("Note for your records (empty for none)")
+            .allow_empty(true)
+            .interact_text()?;
+        let note = Some(note.trim().to_string()).filter(|n| !n.is_empty());
+
+        let comment = self
+            .compose_feedback(session, &summary, &grade, recording.as_ref())
+            .await?;
+
+        if let Some(cast) = recording.and_then(record::Recording::stop) {
+            println!("Session recorded to {}", cast.display());
+        }
+
+        let removed = snapshot.restore(&injected).await?;
+        if removed > 0 {
+            println!("Removed {} injected or generated files", removed);
+        }
+
+        Ok(Graded::Done(Outcome {
+            score: grade.score,
+            comment,
+            rubric: grade.rubric,
+            annotations,
+            note,
+        }))
+    }
+
+    /// Starts a comment from the assignment's template and, if opted in, an
+    /// LLM draft, then opens it for editing. Nothing is ever posted from here.
+    async fn compose_feedback(
+        &self,
+        session: &Session,
+        summary: &[(String, bool)],
+        grade: &Grade<'_>,
+        recording: Option<&record::Recording>,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let mut comment = match &session.template {
+            Some(t) => template::expand(t, &self.template_vars(session, summary, grade)),
+            None => match &grade.rubric {
+                Some(assessment) => assessment.itemize(),
+                None => deductions::itemize(&grade.deductions),
+            },
+        };
+
+        if let Some(drafter) = llm::Drafter::from_env() {
+            if Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Draft feedback with the LLM?")
+                .default(false)
+                .interact()?
+            {
+                let notes = Input::<String>::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Notes")
+                    .allow_empty(true)
+                    .interact()?;
+
+                println!("Drafting feedback...");
+
+                let checks = summary
+                    .iter()
+                    .map(|(check, passed)| {
+                        format!("{check}: {}", if *passed { "passed" } else { "failed" })
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                if !comment.is_empty() {
+                    comment.push_str("\n\n");
+                }
+                let starter = session
+                    .assignment_dir
+                    .as_ref()
+                    .map(|d| d.join(config::STARTER))
+                    .filter(|d| d.is_dir());
+                let diff = match starter {
+                    Some(starter) => Some(diff::between(&starter, &self.path).await?),
+                    None => None,
                 };
 
-                println!("\t{} {}", contains, f.name);
-            });
+                comment.push_str(&drafter.draft(&checks, &notes, diff.as_deref()).await?);
+            }
+        }
 
-        if !query_continue_or_exit()? {
-            exit(0);
+        if comment.is_empty() {
+            return Ok(None);
         }
 
-        files
+        let path = self.feedback_path();
+        fs::write(&path, comment).await?;
+        let comment = self.edit_comment(session, &path, recording).await?;
+
+        println!("Feedback saved to {}", path.display());
+
+        Ok(Some(comment))
+    }
+
+    fn template_vars(
+        &self,
+        session: &Session,
+        summary: &[(String, bool)],
+        grade: &Grade<'_>,
+    ) -> HashMap<&'static str, String> {
+        let name = &self.name;
+        let (last, first) = name
+            .split_once(',')
+            .map(|(last, first)| (last.trim(), first.trim()))
+            .unwrap_or((name.as_str(), name.as_str()));
+
+        let failed: Vec<_> = summary
             .iter()
-            .filter(|f| re.is_match(&f.name.to_lowercase()))
-            .map(File::open_file_in_editor)
-            .collect::<Result<_, _>>()?;
+            .filter(|(_, passed)| !passed)
+            .map(|(check, _)| format!("- {check}"))
+            .collect();
+
+        let penalty = session
+            .late_policy
+            .as_ref()
+            .map_or(0.0, |p| self.lateness.penalty(p));
+
+        let mut vars = HashMap::from([
+            ("first_name", first.to_string()),
+            ("last_name", last.to_string()),
+            ("name", format!("{first} {last}")),
+            (
+                "failed_checks",
+                match failed.is_empty() {
+                    true => "none".into(),
+                    false => failed.join("\n"),
+                },
+            ),
+            ("late_penalty", format!("{penalty}%")),
+            ("deductions", deductions::itemize(&grade.deductions)),
+            (
+                "rubric",
+                grade
+                    .rubric
+                    .as_ref()
+                    .map(|a| a.itemize())
+                    .unwrap_or_default(),
+            ),
+        ]);
+
+        if let Some(score) = grade.score {
+            vars.insert("score", score.to_string());
+        }
+
+        if let Some(points) = session.points_possible {
+            vars.insert("points_possible", points.to_string());
+        }
+
+        vars
+    }
+
+    /// Opens the comment in the editor until it passes the spell check and
+    /// the leak guard (or the grader overrides them).
+    async fn edit_comment(
+        &self,
+        session: &Session,
+        path: &Path,
+        recording: Option<&record::Recording>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        loop {
+            open_in_editor(path, recording)?;
+
+            let comment = fs::read_to_string(path).await?;
+
+            if spell::review(&comment).await? && session.guard.review(&self.members(), &comment)? {
+                return Ok(comment);
+            }
+        }
+    }
+
+    /// Picks the comment to post with the grade, checked like the feedback.
+    async fn choose_comment(
+        &self,
+        session: &Session,
+        feedback: Option<&str>,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let sources: Vec<_> = CommentSource::iter()
+            .filter(|s| feedback.is_some() || *s != CommentSource::Feedback)
+            .filter(|s| !session.snippets.is_empty() || *s != CommentSource::Snippets)
+            .collect();
+
+        loop {
+            let source = sources[Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Comment")
+                .items(&sources)
+                .default(0)
+                .interact()?];
+
+            let comment = match source {
+                CommentSource::Feedback => return Ok(feedback.map(String::from)),
+                CommentSource::Typed => Input::<String>::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Comment")
+                    .interact_text()?,
+                CommentSource::Snippets => snippets::compose(&session.snippets, &self.path)?,
+                CommentSource::File => {
+                    let path = Input::<String>::with_theme(&ColorfulTheme::default())
+                        .with_prompt("File")
+                        .interact_text()?;
+
+                    match fs::read_to_string(path.trim()).await {
+                        Ok(comment) => comment,
+                        Err(e) => {
+                            println!("{} {}", "error:".red(), e);
+                            continue;
+                        }
+                    }
+                }
+                CommentSource::None => return Ok(None),
+            };
 
-        // use blocking command otherwise the inheritting of file descriptors
-        // seems to deadlock the program
-        Command::new("sh")
-            .arg("-c")
-            .arg(format!("cd '{}'; exec ${{SHELL:-sh}}", self.path.display()))
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .output()?;
+            if spell::review(&comment).await? && session.guard.review(&self.members(), &comment)? {
+                return Ok(Some(comment));
+            }
+        }
+    }
 
-        Ok(())
+    fn feedback_path(&self) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(".feedback.txt");
+        name.into()
     }
 }
 
@@ -153,160 +1059,1984 @@ struct File {
 }
 
 impl File {
-    fn open_file_in_editor(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let editor = env::var("EDITOR").unwrap_or("vi".into());
+    fn open_file_in_editor(
+        &self,
+        recording: Option<&record::Recording>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        open_in_editor(&self.path, recording)
+    }
+}
 
-        Command::new(editor)
-            .arg(&self.path)
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .output()?;
+/// The last name out of a sortable "Last, First" name.
+fn last_name(sortable_name: &str) -> &str {
+    sortable_name.split(',').next().unwrap_or_default().trim()
+}
 
-        Ok(())
+/// The last names of whoever made the submission, every member of a group.
+fn last_names<'a>(name: &'a str, group: Option<&'a groups::GroupSubmission>) -> Vec<&'a str> {
+    match group {
+        Some(group) => group.names.iter().map(|n| last_name(n)).collect(),
+        None => vec![last_name(name)],
     }
 }
 
-async fn fetch_user_profile(
-    canvas: &CanvasInformation<'_>,
-    user_id: usize,
-) -> Result<UserProfile, Box<dyn std::error::Error>> {
-    Ok(UserProfile::get_user_profile(user_id)?
-        .fetch(canvas)
-        .await?
-        .inner())
+/// Canvas stores text entries as html, keep just the text.
+fn html_to_text(html: &str) -> String {
+    let breaks = Regex::new(r"(?i)<br\s*/?>|</p>|</div>|</li>").unwrap();
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+
+    let text = breaks.replace_all(html, "\n");
+    let text = tags.replace_all(&text, "");
+
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
 }
 
-fn query_continue_or_exit() -> Result<bool, Box<dyn std::error::Error>> {
-    Ok(Confirm::with_theme(&ColorfulTheme::default())
-        .with_prompt("Do you want to continue?")
-        .interact()?)
+async fn extract<R>(archive: R, path: &Path) -> Result<(), Box<dyn std::error::Error>>
+where
+    R: std::io::Read + std::io::Seek + Send + 'static,
+{
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || zip_extract::extract(archive, &path, true)).await??;
+
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    dotenv().ok();
+fn open_in_editor(
+    path: &Path,
+    recording: Option<&record::Recording>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let editor = env::var("EDITOR").unwrap_or("vi".into());
 
-    let base_url = env::var("CANVAS_BASE_URL").unwrap();
-    let access_token = env::var("CANVAS_ACCESS_TOKEN").unwrap();
-    let canvas = CanvasInformation::new(&base_url, &access_token);
+    record::command(&editor, [path.to_string_lossy()], recording)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
 
-    println!("Loading courses...");
+    Ok(())
+}
 
-    let courses = Canvas::get_courses()?.fetch(&canvas).await?.inner();
+fn open_shell(
+    path: &Path,
+    recording: Option<&record::Recording>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // use blocking command otherwise the inheritting of file descriptors
+    // seems to deadlock the program
+    record::command(
+        "sh",
+        [
+            "-c",
+            format!(
+                "cd {}; exec ${{SHELL:-sh}}",
+                sandbox::quote(&path.to_string_lossy())
+            )
+            .as_str(),
+        ],
+        recording,
+    )
+    .stdin(Stdio::inherit())
+    .stdout(Stdio::inherit())
+    .stderr(Stdio::inherit())
+    .output()?;
 
-    let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
-        .with_prompt("Course")
-        .items(
-            &courses
-                .iter()
-                .filter_map(|c| c.name.clone())
-                .collect::<Box<_>>(),
-        )
-        .interact()?;
+    Ok(())
+}
 
-    let course = &courses[selection];
+async fn fetch_user_profile(
+    canvas: &CanvasInformation<'_>,
+    user_id: usize,
+) -> Result<UserProfile, Box<dyn std::error::Error>> {
+    retry::retry(|| async {
+        Ok(UserProfile::get_user_profile(user_id)?
+            .fetch(canvas)
+            .await?
+            .inner())
+    })
+    .await
+}
 
-    println!("Loading assignments...");
+/// Assignment groups (when there is more than one) and modules to narrow the
+/// assignment list to, with the assignments in each.
+async fn assignment_filters(
+    client: &canvas::Client,
+    course_id: usize,
+    summaries: &[canvas::AssignmentSummary],
+) -> Vec<(String, HashSet<usize>)> {
+    let mut filters = vec![];
 
-    let assignments: Vec<_> = course.get_assignments()?.fetch(&canvas).await?.inner();
+    let groups = client
+        .get_assignment_groups(course_id)
+        .await
+        .unwrap_or_default();
 
-    let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
-        .with_prompt("Assignment")
-        .items(
-            &assignments
+    if groups.len() > 1 {
+        filters.extend(groups.into_iter().map(|g| {
+            let ids = summaries
                 .iter()
-                .filter_map(|a| a.name.clone())
-                .collect::<Vec<_>>(),
-        )
-        .interact()?;
-
-    let assignment = &assignments[selection];
+                .filter(|s| s.assignment_group_id == Some(g.id))
+                .map(|s| s.id)
+                .collect();
 
-    println!("Fetching available submissions...");
+            (format!("Group: {}", g.name), ids)
+        }));
+    }
 
-    let submissions = assignment.get_submissions()?.fetch(&canvas).await?.inner();
+    let modules = client.get_modules(course_id).await.unwrap_or_default();
 
-    let total_submissions = submissions.len();
+    filters.extend(modules.into_iter().filter_map(|m| {
+        let ids: HashSet<_> = m
+            .items
+            .iter()
+            .filter(|i| i.kind == "Assignment")
+            .filter_map(|i| i.content_id)
+            .collect();
 
-    let division_count = Input::<usize>::with_theme(&ColorfulTheme::default())
-        .with_prompt("Divison Count")
-        .interact()?;
+        (!ids.is_empty()).then(|| (format!("Module: {}", m.name), ids))
+    }));
 
-    let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
-        .with_prompt("Portion")
-        .default(0)
-        .items(
-            &(1..=division_count)
-                .map(|i| i.to_string())
-                .collect::<Vec<_>>(),
-        )
-        .interact()?;
+    filters
+}
 
-    let portion_length = total_submissions / division_count;
-    let start = portion_length * selection;
-    let end = if selection < division_count - 1 {
-        start + portion_length
-    } else {
-        start + portion_length + (total_submissions % division_count)
+/// The assignment's name with how many submissions need grading and when it
+/// is due, e.g. "Lab 5 (12 to grade, due Fri Oct  3 23:59)".
+fn assignment_label(name: &str, summary: Option<&canvas::AssignmentSummary>) -> String {
+    let Some(summary) = summary else {
+        return name.to_string();
     };
 
-    let user_ids: Box<_> = submissions.iter().map(|s| s.user_id.unwrap()).collect();
+    let mut details = vec![];
 
-    println!("Fetching selected portion...");
+    if let Some(count) = summary.needs_grading_count {
+        details.push(format!("{count} to grade"));
+    }
 
-    let user_profiles = user_ids
-        .into_iter()
-        .map(|&id| fetch_user_profile(&canvas, id))
-        .collect::<FuturesOrdered<_>>()
-        .collect::<Vec<_>>()
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(due_at) = summary.due_at {
+        details.push(format!(
+            "due {}",
+            due_at.with_timezone(&Local).format("%a %b %e %H:%M")
+        ));
+    }
+
+    match details.is_empty() {
+        true => name.to_string(),
+        false => format!("{} ({})", name, details.join(", ")),
+    }
+}
 
-    let mut user_submissions: Vec<_> = submissions
+/// One student's submission, or `None` when they have not submitted.
+async fn student_submission(
+    client: &canvas::Client,
+    course_id: usize,
+    assignment: &Assignment,
+    student: &canvas::User,
+) -> Result<Option<UserSubmission>, Box<dyn std::error::Error>> {
+    let submission = client
+        .get_submissions(course_id, assignment.id)
+        .await?
         .into_iter()
-        .zip(user_profiles.into_iter())
-        .map(|(submission, user_profile)| {
-            Some(UserSubmission {
-                submission,
-                user_profile,
-            })
-        })
-        .collect();
+        .find(|s| s.user_id == Some(student.id));
 
-    user_submissions.sort_by(|a, b| {
-        a.as_ref()
-            .unwrap()
-            .user_profile
-            .sortable_name
-            .cmp(&b.as_ref().unwrap().user_profile.sortable_name)
-    });
+    let Some(submission) = submission.filter(|s| s.submitted_at.is_some()) else {
+        return Ok(None);
+    };
 
-    let mut user_submissions: Vec<_> = user_submissions.drain(start..end).collect();
+    let due_dates = DueDates::fetch(
+        client,
+        course_id,
+        assignment.id,
+        assignment.due_at.as_deref().and_then(|d| d.parse().ok()),
+    )
+    .await?;
 
-    let selections = MultiSelect::with_theme(&ColorfulTheme::default())
-        .with_prompt("Users to grade")
-        .items(
-            &user_submissions
-                .iter()
-                .map(|d| &d.as_ref().unwrap().user_profile.sortable_name)
-                .collect::<Box<_>>(),
-        )
-        .defaults(&vec![true; user_submissions.len()])
-        .interact()?;
+    Ok(Some(UserSubmission {
+        user_id: student.id,
+        sortable_name: student.sortable_name.clone(),
+        due_at: due_dates.for_user(student.id),
+        submission,
+        group: None,
+        attempt: None,
+    }))
+}
 
-    for s in selections {
-        let mut submission = None;
-        std::mem::swap(&mut user_submissions[s], &mut submission);
+/// Downloads one student's submission into `path`, or returns `None` when they
+/// have not submitted.
+async fn download_student(
+    client: &canvas::Client,
+    course_id: usize,
+    assignment: &Assignment,
+    student: &canvas::User,
+    roster: &roster::Roster,
+    path: PathBuf,
+) -> Result<Option<DownloadedSubmission>, Box<dyn std::error::Error>> {
+    let Some(submission) = student_submission(client, course_id, assignment, student).await? else {
+        return Ok(None);
+    };
+
+    let name = submission.display_name(roster);
+
+    Ok(Some(
+        submission
+            .download_submission(client, course_id, assignment.id, name, path)
+            .await?,
+    ))
+}
+
+/// Asks which attempt to grade when the student submitted more than once.
+async fn choose_attempt(
+    client: &canvas::Client,
+    course_id: usize,
+    assignment_id: usize,
+    submission: &UserSubmission,
+    name: &str,
+) -> Result<Option<canvas::Attempt>, Box<dyn std::error::Error>> {
+    let mut attempts = client
+        .get_submission_history(course_id, assignment_id, submission.user_id)
+        .await?;
+
+    if attempts.len() < 2 {
+        return Ok(None);
+    }
+
+    let items: Vec<_> = attempts
+        .iter()
+        .map(|a| {
+            format!(
+                "Attempt {} ({})",
+                a.attempt.map(|n| n.to_string()).unwrap_or_default(),
+                Lateness::new(a.submitted_at, submission.due_at)
+            )
+        })
+        .collect();
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Attempt to grade for {}", name))
+        .items(&items)
+        .default(items.len() - 1)
+        .interact()?;
+
+    Ok(Some(attempts.swap_remove(selection)))
+}
+
+/// Finds a student by part of their name, login or email, asking which one
+/// when several match.
+fn select_student<'a>(
+    students: &'a [canvas::User],
+    key: &str,
+) -> Result<&'a canvas::User, Box<dyn std::error::Error>> {
+    let key = key.trim().to_lowercase();
+
+    let matches: Vec<_> = students
+        .iter()
+        .filter(|s| {
+            [
+                Some(&s.name),
+                Some(&s.sortable_name),
+                s.login_id.as_ref(),
+                s.email.as_ref(),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|field| field.to_lowercase().contains(&key))
+        })
+        .collect();
+
+    match matches.len() {
+        0 => Err(Errors::StudentNotFound.into()),
+        1 => Ok(matches[0]),
+        _ => {
+            let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Student")
+                .items(&matches.iter().map(|s| &s.sortable_name).collect::<Vec<_>>())
+                .interact()?;
+
+            Ok(matches[selection])
+        }
+    }
+}
+
+/// Hashes every file in the cohort and reports identical and similar ones
+/// up front.
+fn flag_duplicates(
+    session: &mut Session,
+    submissions: &[DownloadedSubmission],
+    archives: &[PathBuf],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let assignment_dir = session.assignment_dir.as_deref();
+    let starter = assignment_dir.map(|dir| dir.join(config::STARTER));
+
+    // the assignment's own archive of previous semesters is always included
+    let mut archives = archives.to_vec();
+    archives.extend(
+        assignment_dir
+            .map(|dir| dir.join(config::ARCHIVE))
+            .filter(|dir| dir.is_dir()),
+    );
+
+    let archived = integrity::load_archives(&archives)?;
+
+    session.duplicates = integrity::find_duplicates(submissions, &archived, starter.as_deref())?;
+    session.similar = similarity::find(submissions, starter.as_deref(), &session.settings);
+
+    if !session.duplicates.is_empty() {
+        println!("Identical files between submissions:");
+
+        for s in submissions {
+            if let Some(duplicates) = session.duplicates.get(&s.user_id) {
+                println!("{}", s.name.bright_blue());
+                integrity::print_duplicates(duplicates);
+            }
+        }
+    }
+
+    if !session.similar.is_empty() {
+        println!("Similar submissions:");
+
+        for s in submissions {
+            if let Some(similar) = session.similar.get(&s.user_id) {
+                println!("{}", s.name.bright_blue());
+                similarity::print(similar);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Offers to record a zero with a comment, to every member of a group, for a
+/// student who never submitted.
+async fn record_missing(
+    client: &canvas::Client,
+    course_id: usize,
+    assignment: &Assignment,
+    session: &Session,
+    submission: &UserSubmission,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if session.is_excused(submission.user_id) {
+        println!(
+            "{} is excused, not recording a zero",
+            submission.display_name(&session.roster)
+        );
+        return Ok(());
+    }
+
+    println!("Press z to record a zero with a comment, any other key to skip");
+
+    let Key::Char('z') = Term::stdout().read_key()? else {
+        return Ok(());
+    };
+
+    let comment = Input::<String>::with_theme(&ColorfulTheme::default())
+        .with_prompt("Comment")
+        .with_initial_text(MISSING_COMMENT)
+        .allow_empty(true)
+        .interact_text()?;
+
+    let members = match &submission.group {
+        Some(group) => group.members.clone(),
+        None => vec![submission.user_id],
+    };
+
+    for user_id in members {
+        let mut record = history::Record::new(
+            course_id,
+            assignment.id,
+            assignment.name.as_deref().unwrap_or_default(),
+            user_id,
+        );
+
+        record.previous_score = client
+            .get_submission_grade(course_id, assignment.id, user_id)
+            .await?
+            .score;
+
+        match session.moderated {
+            true => {
+                client
+                    .put_provisional_grade(course_id, assignment.id, user_id, 0.0)
+                    .await?
+            }
+            false => {
+                client
+                    .put_grade(course_id, assignment.id, user_id, 0.0)
+                    .await?
+            }
+        }
+        record.score = Some(0.0);
+        record.provisional = session.moderated;
+
+        if !comment.trim().is_empty() {
+            record.comment_id = client
+                .put_comment(course_id, assignment.id, user_id, &comment, &[])
+                .await?;
+            record.comment = Some(comment.clone());
+        }
+
+        history::append(&record).await?;
+    }
+
+    println!("Recorded a zero");
+
+    Ok(())
+}
+
+/// Lists the files attached to the student's submission comments and saves
+/// them to `comments/` in their directory.
+async fn download_comment_files(
+    session: &Session,
+    submission: &DownloadedSubmission,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(existing) = session.existing.get(&submission.user_id) else {
+        return Ok(());
+    };
+
+    let mut files = vec![];
+
+    for comment in &existing.submission_comments {
+        for attachment in &comment.attachments {
+            println!(
+                "\t{} attached {}{}",
+                comment.author_name.as_deref().unwrap_or("unknown"),
+                attachment.display_name.bright_blue(),
+                comment
+                    .created_at
+                    .map(|at| format!(" on {}", at.with_timezone(&Local).format("%a %b %e %H:%M")))
+                    .unwrap_or_default()
+            );
+
+            files.push(attachment);
+        }
+    }
+
+    if !files.is_empty() {
+        attachments::save(&files, &submission.path.join(COMMENT_FILES)).await?;
+    }
+
+    Ok(())
+}
+
+/// The score and comments a submission already has on canvas.
+fn print_existing(existing: &canvas::SubmissionGrade, graders: &HashMap<usize, String>) {
+    if existing.score.is_none()
+        && existing.submission_comments.is_empty()
+        && existing.provisional_grades.is_empty()
+    {
+        return;
+    }
+
+    println!("Already on canvas:");
+
+    if let Some(score) = existing.score {
+        let grader = existing
+            .grader_id
+            .and_then(|id| usize::try_from(id).ok())
+            .and_then(|id| graders.get(&id))
+            .map(String::as_str)
+            .unwrap_or("unknown grader");
+
+        match existing.graded_at {
+            Some(graded_at) => println!(
+                "\tScore {} by {} on {}",
+                score.to_string().bright_blue(),
+                grader,
+                graded_at.with_timezone(&Local).format("%a %b %e %H:%M")
+            ),
+            None => println!("\tScore {} by {}", score.to_string().bright_blue(), grader),
+        }
+    }
+
+    for provisional in &existing.provisional_grades {
+        let grader = provisional
+            .scorer_id
+            .and_then(|id| graders.get(&id))
+            .map(String::as_str)
+            .unwrap_or("unknown grader");
+
+        let score = provisional
+            .score
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "no score".into());
+
+        match provisional.is_final {
+            true => println!(
+                "\tProvisional {} by {} (final)",
+                score.bright_blue(),
+                grader
+            ),
+            false => println!("\tProvisional {} by {}", score.bright_blue(), grader),
+        }
+    }
+
+    for comment in &existing.submission_comments {
+        println!(
+            "\tComment by {}:",
+            comment.author_name.as_deref().unwrap_or("unknown")
+        );
+
+        for line in comment.comment.as_deref().unwrap_or_default().lines() {
+            println!("\t\t{}", line.dimmed());
+        }
+    }
+}
+
+/// What each peer reviewer said about the submission.
+fn print_peer_reviews(reviews: &[canvas::PeerReview]) {
+    println!("Peer reviews:");
+
+    for review in reviews {
+        let reviewer = review
+            .assessor
+            .as_ref()
+            .and_then(|a| a.display_name.as_deref())
+            .unwrap_or("anonymous");
+
+        match review.workflow_state.as_str() {
+            "completed" => println!("\t{}:", reviewer.bright_blue()),
+            state => println!("\t{} ({}):", reviewer.bright_blue(), state),
+        }
+
+        if review.submission_comments.is_empty() {
+            println!("\t\t{}", "no comments".dimmed());
+        }
+
+        for comment in &review.submission_comments {
+            for line in comment.comment.as_deref().unwrap_or_default().lines() {
+                println!("\t\t{}", line.dimmed());
+            }
+        }
+    }
+}
+
+/// Scores per student across every assignment graded in the session.
+fn print_report(report: &[(&str, &str, Option<f64>)]) {
+    let mut by_student: BTreeMap<&str, Vec<(&str, Option<f64>)>> = BTreeMap::new();
+
+    for &(student, assignment, score) in report {
+        by_student
+            .entry(student)
+            .or_default()
+            .push((assignment, score));
+    }
+
+    println!("Report:");
+
+    for (student, scores) in by_student {
+        println!("\t{}", student.bright_blue());
+
+        for (assignment, score) in scores {
+            match score {
+                Some(score) => println!("\t\t{}: {}", assignment, score),
+                None => println!("\t\t{}: {}", assignment, "no score".dimmed()),
+            }
+        }
+    }
+}
+
+/// Which of `division_count` portions a student is in. The same student
+/// always lands in the same portion, however many others have submitted.
+fn portion_of(user_id: usize, division_count: usize) -> usize {
+    (mix(user_id as u64) % division_count as u64) as usize
+}
+
+/// splitmix64's finalizer, so consecutive inputs are spread evenly; the
+/// standard library's hasher may change between releases.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Fisher-Yates with splitmix64, the same order for the same seed and items.
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed;
+
+    for i in (1..items.len()).rev() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        items.swap(i, (mix(state) % (i as u64 + 1)) as usize);
+    }
+}
+
+/// The batch check phase, run once everything is downloaded.
+async fn run_checks(
+    session: &mut Session,
+    submissions: &[DownloadedSubmission],
+) -> Result<(), Box<dyn std::error::Error>> {
+    if session.checks.is_empty() || submissions.is_empty() {
+        return Ok(());
+    }
+
+    session.check_results = checks::run_batch(
+        &session.checks,
+        session.assignment_dir.as_deref(),
+        submissions,
+        session.sandbox,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Offers to show students the grades posted to them while grades were held.
+async fn offer_release(
+    client: &canvas::Client,
+    assignment: &Assignment,
+    students: &[usize],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let name = assignment.name.as_deref().unwrap_or_default();
+
+    match Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "Release the {} grades posted for {} to students?",
+            students.len(),
+            name
+        ))
+        .default(true)
+        .interact()?
+    {
+        true => {
+            post_policy::release(client, assignment.id, Some(students)).await?;
+            println!("Released grades for {}", name);
+        }
+        false => println!(
+            "Grades for {} stay hidden, release them with post-grades",
+            name
+        ),
+    }
+
+    Ok(())
+}
+
+/// Puts back the post policy `--hold-grades` changed, only warning when it
+/// can't be so the other assignments' are still put back.
+async fn restore_policy(client: &canvas::Client, assignment: &Assignment, held: bool) {
+    if let Err(e) = post_policy::restore(client, assignment.id, held).await {
+        println!(
+            "{} couldn't put back the post policy of {}: {}",
+            "warning:".yellow(),
+            assignment.name.as_deref().unwrap_or_default(),
+            e
+        );
+    }
+}
+
+/// Asks for the comment and posts it with the score given while grading to
+/// canvas (to every member of a group, but the score not to those excused)
+/// once confirmed. Returns the score posted, if any.
+async fn post_grade(
+    client: &canvas::Client,
+    course_id: usize,
+    assignment: &Assignment,
+    session: &Session,
+    submission: &DownloadedSubmission,
+    outcome: &Outcome<'_>,
+) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+    let theme = ColorfulTheme::default();
+
+    // group grades go to every member
+    let members = submission.members();
+    let excused: Vec<_> = members
+        .iter()
+        .copied()
+        .filter(|&id| session.is_excused(id))
+        .collect();
+
+    let score = match excused.len() == members.len() {
+        true => {
+            println!(
+                "{} {} is excused, only a comment can be posted",
+                "warning:".yellow(),
+                submission.name
+            );
+            None
+        }
+        false => {
+            if !excused.is_empty() {
+                let names: Vec<_> = excused
+                    .iter()
+                    .map(|id| {
+                        session
+                            .students
+                            .get(id)
+                            .map_or_else(|| id.to_string(), |u| u.name.clone())
+                    })
+                    .collect();
+
+                println!(
+                    "{} {} excused, only the comment is posted to them",
+                    "warning:".yellow(),
+                    names.join(", ")
+                );
+            }
+
+            outcome.score
+        }
+    };
+    let comment = submission
+        .choose_comment(session, outcome.comment.as_deref())
+        .await?;
+
+    // the rubric breakdown goes with the score, but not with a provisional
+    // one the moderator may not pick
+    let rubric = outcome
+        .rubric
+        .as_ref()
+        .filter(|_| score.is_some() && !session.moderated);
+
+    let mut posting = vec![];
+    if let Some(score) = score {
+        posting.push(match (rubric, session.moderated) {
+            (Some(_), _) => format!("{score} with the rubric"),
+            (None, true) => format!("{score} as a provisional grade"),
+            (None, false) => score.to_string(),
+        });
+    }
+    if comment.is_some() {
+        posting.push("the comment".into());
+    }
+    if !outcome.annotations.is_empty() {
+        posting.push(format!("{} annotated files", outcome.annotations.len()));
+    }
+
+    if posting.is_empty() {
+        return Ok(None);
+    }
+    let posting = posting.join(" and ");
+
+    if !Confirm::with_theme(&theme)
+        .with_prompt(format!("Post {} for {}?", posting, submission.name))
+        .default(true)
+        .interact()?
+    {
+        return Ok(None);
+    }
+
+    for user_id in members {
+        let mut record = history::Record::new(
+            course_id,
+            assignment.id,
+            assignment.name.as_deref().unwrap_or_default(),
+            user_id,
+        );
+        record.attempt = submission.attempt;
+
+        if let Some(score) = score.filter(|_| !excused.contains(&user_id)) {
+            record.previous_score = client
+                .get_submission_grade(course_id, assignment.id, user_id)
+                .await?
+                .score;
+
+            if let Some(assessment) = rubric {
+                client
+                    .put_rubric_assessment(course_id, assignment.id, user_id, &assessment.form())
+                    .await?;
+            }
+
+            match session.moderated {
+                true => {
+                    client
+                        .put_provisional_grade(course_id, assignment.id, user_id, score)
+                        .await?
+                }
+                false => {
+                    client
+                        .put_grade(course_id, assignment.id, user_id, score)
+                        .await?
+                }
+            }
+
+            record.score = Some(score);
+            record.provisional = session.moderated;
+        }
+
+        if comment.is_some() || !outcome.annotations.is_empty() {
+            let mut file_ids = vec![];
+            for path in &outcome.annotations {
+                file_ids.push(
+                    client
+                        .upload_comment_file(course_id, assignment.id, user_id, path)
+                        .await?,
+                );
+            }
+
+            record.comment_id = client
+                .put_comment(
+                    course_id,
+                    assignment.id,
+                    user_id,
+                    comment.as_deref().unwrap_or_default(),
+                    &file_ids,
+                )
+                .await?;
+
+            record.comment = comment.clone();
+        }
+
+        history::append(&record).await?;
+    }
+
+    println!("Posted {} to canvas", posting);
+
+    Ok(score)
+}
+
+/// Asks for a score, `None` when left empty. Fractions of a point are fine, a
+/// negative score or one above the points possible has to be confirmed since
+/// it is usually a typo.
+fn read_score(points_possible: Option<f64>) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+    let theme = ColorfulTheme::default();
+
+    loop {
+        let mut input = Input::<String>::with_theme(&theme);
+        input
+            .with_prompt(match points_possible {
+                Some(points) => format!("Score out of {points} (empty to skip)"),
+                None => "Score (empty to skip)".into(),
+            })
+            .allow_empty(true)
+            .validate_with(|s: &String| -> Result<(), &str> {
+                match s.trim().is_empty() || s.trim().parse::<f64>().is_ok_and(f64::is_finite) {
+                    true => Ok(()),
+                    false => Err("not a number"),
+                }
+            });
+        let Ok(score) = input.interact_text()?.trim().parse::<f64>() else {
+            return Ok(None);
+        };
+
+        let problem = match points_possible {
+            _ if score < 0.0 => format!("{score} is negative"),
+            Some(points) if score > points => {
+                format!("{score} is more than the {points} points possible")
+            }
+            _ => return Ok(Some(score)),
+        };
+
+        println!("{} {}", "warning:".yellow(), problem);
+
+        if Confirm::with_theme(&theme)
+            .with_prompt("Use it anyway?")
+            .default(false)
+            .interact()?
+        {
+            return Ok(Some(score));
+        }
+    }
+}
+
+fn query_next() -> Result<Next, Box<dyn std::error::Error>> {
+    let choices: Vec<_> = Next::iter().collect();
+
+    Ok(choices[Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Do you want to continue?")
+        .items(&choices)
+        .default(0)
+        .interact()?])
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+
+    let cli = Cli::parse();
+
+    // a regrade is usually of a student someone else claimed
+    let claims_dir = cli.claims.as_deref().filter(|_| !cli.regrade);
+
+    let options = Options {
+        record: cli.record,
+        build_by_default: cli.build,
+        sandbox: cli.sandbox.unwrap_or_default(),
+    };
+
+    let roster = match &cli.roster {
+        Some(path) => roster::Roster::load(path)?,
+        None => roster::Roster::default(),
+    };
+
+    if let Some(Commands::Local {
+        dir,
+        assignment,
+        points,
+        output,
+    }) = &cli.command
+    {
+        return local::run(
+            dir,
+            assignment.as_deref(),
+            *points,
+            output,
+            roster,
+            &cli.archive,
+            options,
+        )
+        .await;
+    }
+
+    if let Some(Commands::Gradescope {
+        dir,
+        assignment,
+        output,
+        visibility,
+    }) = &cli.command
+    {
+        let assignment_dir = assignment
+            .as_deref()
+            .and_then(|name| config::assignment_dir(None, name));
+
+        let checks = checks::load(assignment_dir.as_deref(), options.build_by_default).await;
+
+        inject::inject(assignment_dir.as_deref(), dir).await?;
+
+        return gradescope::run(&checks, dir, output, *visibility, options.sandbox).await;
+    }
+
+    let profile = profiles::select(cli.profile.as_deref()).await?;
+
+    if let Some(Commands::SaveToken) = &cli.command {
+        return secrets::save_token(&profile.base_url);
+    }
+
+    let access_token = oauth::access_token(&profile).await?;
+    let canvas = CanvasInformation::new(&profile.base_url, &access_token);
+    let client = canvas::Client::new(&profile.base_url, oauth::Tokens::new(&profile), cli.refresh);
+
+    if let Some(Commands::Export { output }) = &cli.command {
+        return export::run(&client, output).await;
+    }
+
+    println!("Loading courses...");
+
+    let mut courses = client.get_courses().await?;
+    courses.retain(|c| c.name.is_some());
+
+    if cli.current_term {
+        courses.retain(|c| c.term.as_ref().map_or(true, canvas::Term::is_current));
+    }
+
+    if cli.favorites {
+        let favorites: HashSet<_> = client
+            .get_favorite_courses()
+            .await?
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+
+        courses.retain(|c| favorites.contains(&c.id));
+    }
+
+    let mut resumed = match cli.command {
+        None if !cli.regrade => resume::offer().await?,
+        _ => None,
+    };
+
+    let resumed_course = resumed
+        .as_ref()
+        .and_then(|r| courses.iter().position(|c| c.id == r.course_id));
+
+    let selection = match resumed_course {
+        Some(i) => i,
+        None => {
+            resumed = None;
+
+            FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Course")
+                .items(
+                    &courses
+                        .iter()
+                        .map(|c| {
+                            let name = c.name.as_deref().unwrap_or_default();
+
+                            match &c.term {
+                                Some(term) => format!("{} ({})", name, term.name),
+                                None => name.to_string(),
+                            }
+                        })
+                        .collect::<Box<_>>(),
+                )
+                .interact()?
+        }
+    };
+
+    let course = &courses[selection];
+
+    println!("Loading assignments...");
+
+    let (mut assignments, summaries) = client.get_assignments_with_summaries(course.id).await?;
+    let summary = |id| summaries.iter().find(|s| s.id == id);
+
+    if cli.published {
+        assignments.retain(|a| summary(a.id).map(|s| s.published).unwrap_or(true));
+    }
+
+    if cli.past_due {
+        let now = Utc::now();
+        assignments.retain(|a| {
+            summary(a.id)
+                .and_then(|s| s.due_at)
+                .is_some_and(|d| d < now)
+        });
+    }
+
+    if cli.due_after.is_some() || cli.due_before.is_some() {
+        assignments.retain(|a| {
+            summary(a.id)
+                .and_then(|s| s.due_at)
+                .map(|d| d.with_timezone(&Local).date_naive())
+                .is_some_and(|d| {
+                    cli.due_after.map_or(true, |after| d >= after)
+                        && cli.due_before.map_or(true, |before| d <= before)
+                })
+        });
+    }
+
+    let filters = assignment_filters(&client, course.id, &summaries).await;
+
+    if !filters.is_empty() && resumed.is_none() {
+        let items: Vec<_> = std::iter::once("All assignments")
+            .chain(filters.iter().map(|(label, _)| label.as_str()))
+            .collect();
+
+        let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+            .with_prompt("Assignment group or module")
+            .items(&items)
+            .default(0)
+            .interact()?;
+
+        if selection > 0 {
+            let (_, ids) = &filters[selection - 1];
+            assignments.retain(|a| ids.contains(&a.id));
+        }
+    }
+
+    match cli.sort {
+        Some(AssignmentOrder::Due) => assignments.sort_by_key(|a| {
+            let due_at = summary(a.id).and_then(|s| s.due_at);
+            (due_at.is_none(), due_at)
+        }),
+        Some(AssignmentOrder::Name) => assignments.sort_by(|a, b| a.name.cmp(&b.name)),
+        Some(AssignmentOrder::NeedsGrading) => assignments
+            .sort_by_key(|a| std::cmp::Reverse(summary(a.id).and_then(|s| s.needs_grading_count))),
+        None => {}
+    }
+
+    let assignment_names: Vec<_> = assignments
+        .iter()
+        .map(|a| assignment_label(a.name.as_deref().unwrap_or_default(), summary(a.id)))
+        .collect();
+
+    if let Some(Commands::Dossier { student }) = &cli.command {
+        let selections = MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt("Assignments")
+            .items(&assignment_names)
+            .interact()?;
+
+        let students = client.get_course_students(course.id).await?;
+        let student = select_student(&students, student)?;
+
+        let selected: Vec<_> = selections.into_iter().map(|i| &assignments[i]).collect();
+
+        return dossier::run(&client, course.id, &selected, student, &roster, options).await;
+    }
+
+    let resumed_assignment = resumed.as_ref().and_then(|r| {
+        let id = r.assignment_ids.first()?;
+        assignments.iter().position(|a| a.id == *id)
+    });
+
+    let selection = match resumed_assignment {
+        Some(i) => i,
+        None => {
+            resumed = None;
+
+            FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Assignment")
+                .items(&assignment_names)
+                .interact()?
+        }
+    };
+
+    let assignment = &assignments[selection];
+
+    if let Some(Commands::PostGrades) = &cli.command {
+        post_policy::release(&client, assignment.id, None).await?;
+        println!("Grades released");
+        return Ok(());
+    }
+
+    if let Some(Commands::PeerEval { csv }) = &cli.command {
+        return peer_eval::run(
+            &client,
+            course.id,
+            assignment.id,
+            assignment.name.as_deref().unwrap_or_default(),
+            assignment.points_possible,
+            csv,
+        )
+        .await;
+    }
+
+    if let Some(Commands::Student { name, watch }) = &cli.command {
+        let students = client.get_course_students(course.id).await?;
+        let student = select_student(&students, name)?;
+
+        return office_hours::run(
+            &client, course.id, assignment, student, &roster, *watch, options,
+        )
+        .await;
+    }
+
+    if let Some(Commands::Diff { student }) = &cli.command {
+        let students = client.get_course_students(course.id).await?;
+        let student = select_student(&students, student)?;
+
+        return diff::run(&client, course.id, assignment, student).await;
+    }
+
+    if let Some(Commands::Revert { student }) = &cli.command {
+        let students = client.get_course_students(course.id).await?;
+        let student = match student {
+            Some(key) => Some(select_student(&students, key)?),
+            None => None,
+        };
+
+        return revert::run(&client, course.id, assignment.id, &students, student).await;
+    }
+
+    let mut batch = vec![assignment];
+
+    if let Some(r) = &resumed {
+        batch.extend(
+            r.assignment_ids
+                .iter()
+                .skip(1)
+                .filter_map(|id| assignments.iter().find(|a| a.id == *id)),
+        );
+    }
+
+    while resumed.is_none()
+        && !cli.regrade
+        && Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Grade another assignment in this session?")
+            .default(false)
+            .interact()?
+    {
+        let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+            .with_prompt("Assignment")
+            .items(&assignment_names)
+            .interact()?;
+
+        batch.push(&assignments[selection]);
+    }
+
+    let sections = client.get_sections(course.id).await.unwrap_or_default();
+
+    // students in the chosen sections, everyone when none are chosen
+    let section_students: Option<HashSet<usize>> = match sections.len() {
+        0 | 1 => None,
+        // the portion's students are already known
+        _ if resumed.is_some() || cli.regrade => None,
+        _ => {
+            let selections = MultiSelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Sections (none for all)")
+                .items(&sections.iter().map(|s| &s.name).collect::<Vec<_>>())
+                .interact()?;
+
+            match selections.is_empty() {
+                true => None,
+                false => {
+                    let chosen: Vec<_> = selections.iter().map(|&i| sections[i].id).collect();
+
+                    Some(
+                        client
+                            .get_student_enrollments(course.id)
+                            .await?
+                            .into_iter()
+                            .filter(|e| chosen.contains(&e.course_section_id))
+                            .map(|e| e.user_id)
+                            .collect(),
+                    )
+                }
+            }
+        }
+    };
+
+    // its profile can't be fetched like a real student's
+    let test_students: HashSet<usize> = match cli.test_student {
+        true => HashSet::new(),
+        false => client
+            .get_test_student_enrollments(course.id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| e.user_id)
+            .collect(),
+    };
+
+    let filters: Vec<_> = StatusFilter::iter().collect();
+    let included: Vec<_> = match resumed {
+        _ if cli.regrade => filters,
+        Some(_) => filters,
+        None => MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt("Submissions to include")
+            .items(&filters)
+            .defaults(&vec![true; filters.len()])
+            .interact()?
+            .into_iter()
+            .map(|i| filters[i])
+            .collect(),
+    };
+
+    // a resumed session's portion is the students saved with it
+    let staff = match (&resumed, &cli.staff) {
+        (None, Some(path)) if !cli.regrade => {
+            let staff = staff::Staff::load(path)?;
+            let me = staff.pick()?;
+            Some((staff, me))
+        }
+        _ => None,
+    };
+
+    let (division_count, portion) = match (&resumed, &staff) {
+        _ if cli.regrade => (1, 0),
+        (Some(_), _) => (1, 0),
+        (None, Some((staff, me))) => (staff.tas.len(), *me),
+        (None, None) => {
+            let division_count = Input::<usize>::with_theme(&ColorfulTheme::default())
+                .with_prompt("Divison Count")
+                .interact()?;
+
+            let portion = FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Portion")
+                .default(0)
+                .items(
+                    &(1..=division_count)
+                        .map(|i| i.to_string())
+                        .collect::<Vec<_>>(),
+                )
+                .interact()?;
+
+            (division_count, portion)
+        }
+    };
+
+    let resuming = resumed.is_some();
+    let mut progress = resumed.unwrap_or_else(|| {
+        resume::Progress::new(
+            course.id,
+            course.name.as_deref().unwrap_or_default(),
+            batch.iter().map(|a| a.id).collect(),
+            match &staff {
+                Some((staff, me)) => staff.tas[*me].clone(),
+                None => format!("{} of {}", portion + 1, division_count),
+            },
+        )
+    });
+
+    // a regrade is one-off grading, which shouldn't replace the session
+    // being worked through
+    if !resuming && !cli.regrade {
+        resume::archive().await?;
+    }
+
+    let orders: Vec<_> = QueueOrder::iter().collect();
+    let queue_order = match cli.regrade {
+        true => QueueOrder::Alphabetical,
+        // a shuffled session is shuffled the same way when resumed
+        false if progress.seed.is_some() => QueueOrder::Random,
+        false => {
+            orders[Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Grading order")
+                .items(&orders)
+                .default(0)
+                .interact()?]
+        }
+    };
+
+    if let QueueOrder::Random = queue_order {
+        progress.seed.get_or_insert_with(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+    }
+
+    let previous_scores: HashMap<_, _> = match queue_order {
+        QueueOrder::PreviousScore => {
+            let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Previous assignment")
+                .items(&assignment_names)
+                .interact()?;
+
+            client
+                .get_submission_grades(course.id, assignments[selection].id)
+                .await?
+                .into_iter()
+                .filter_map(|g| Some((g.user_id, g.score?)))
+                .collect()
+        }
+        _ => HashMap::new(),
+    };
+
+    println!("Loading students...");
+
+    let students = client.get_course_students(course.id).await?;
+
+    // dealt from the whole course, so it doesn't shift as submissions come in
+    let mine = staff.map(|(staff, me)| staff.students(me, &students));
+
+    let graders: HashMap<_, _> = client
+        .get_course_graders(course.id)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|u| (u.id, u.name))
+        .collect();
+
+    let history: Vec<_> = match cli.history {
+        Some(scope) => history::load(scope)
+            .await?
+            .into_iter()
+            .filter(|r| batch.iter().all(|a| a.id != r.assignment_id))
+            .collect(),
+        None => vec![],
+    };
+
+    // (assignment name, session, downloaded submissions) per assignment
+    let mut graded = vec![];
+
+    for assignment in &batch {
+        let assignment_name = assignment.name.clone().unwrap_or_default();
+        let assignment_dir = config::assignment_dir(Some(assignment.id), &assignment_name);
+
+        // each assignment gets its own directory when grading several
+        let workspace = match batch.len() {
+            1 => PathBuf::new(),
+            _ => PathBuf::from(assignment_name.replace('/', "-")),
+        };
+
+        println!("Fetching available submissions for {}...", assignment_name);
+
+        let submissions = client.get_submissions(course.id, assignment.id).await?;
+        let existing: HashMap<usize, canvas::SubmissionGrade> = client
+            .get_submission_feedback(course.id, assignment.id)
+            .await?
+            .into_iter()
+            .map(|s| (s.user_id, s))
+            .collect();
+        let excused = |user_id: usize| existing.get(&user_id).is_some_and(|e| e.is_excused());
+
+        let (submissions, mut groups) = match summary(assignment.id) {
+            Some(s) if s.group_category_id.is_some() && !s.grade_group_students_individually => {
+                let memberships = client
+                    .get_submission_groups(course.id, assignment.id)
+                    .await?;
+                groups::collapse(submissions, &memberships, &students)
+            }
+            _ => (submissions, HashMap::new()),
+        };
+
+        let due_dates = DueDates::fetch(
+            &client,
+            course.id,
+            assignment.id,
+            assignment.due_at.as_deref().and_then(|d| d.parse().ok()),
+        )
+        .await?;
+
+        let submissions: Vec<_> = submissions
+            .into_iter()
+            .filter(|s| {
+                if resuming {
+                    return progress.contains(assignment.id, s.user_id.unwrap());
+                }
+
+                let submitted_at = s.submitted_at.as_deref().and_then(|d| d.parse().ok());
+                let status =
+                    match Lateness::new(submitted_at, due_dates.for_user(s.user_id.unwrap())) {
+                        Lateness::Unsubmitted => StatusFilter::Missing,
+                        Lateness::Late(_) | Lateness::Marked => StatusFilter::Late,
+                        Lateness::NoDueDate | Lateness::OnTime => StatusFilter::OnTime,
+                    };
+                let graded = s.workflow_state.as_deref() == Some("graded");
+                let waiting = matches!(
+                    s.workflow_state.as_deref(),
+                    Some("submitted") | Some("pending_review")
+                );
+                let in_section = section_students
+                    .as_ref()
+                    .map(|students| students.contains(&s.user_id.unwrap()))
+                    .unwrap_or(true);
+
+                in_section
+                    && !test_students.contains(&s.user_id.unwrap())
+                    && (cli.excused || !excused(s.user_id.unwrap()))
+                    && (waiting || !cli.ungraded)
+                    && included.contains(&status)
+                    && (!graded || included.contains(&StatusFilter::Graded))
+            })
+            .collect();
+
+        let total_submissions = submissions.len();
+
+        let portion_length = total_submissions / division_count;
+        let start = portion_length * portion;
+        let end = if portion < division_count - 1 {
+            start + portion_length
+        } else {
+            start + portion_length + (total_submissions % division_count)
+        };
 
-        let d = submission
-            .ok_or(Errors::InvalidSelection)?
-            .download_submission()
+        let user_ids: Box<_> = submissions.iter().map(|s| s.user_id.unwrap()).collect();
+
+        println!("Fetching selected portion...");
+
+        // the course's students are already fetched, anyone else (e.g. a
+        // dropped student) is looked up a hundred per request, then one by
+        // one if that fails
+        let mut names: HashMap<_, _> = students
+            .iter()
+            .map(|s| (s.id, s.sortable_name.clone()))
+            .collect();
+
+        if user_ids.iter().any(|id| !names.contains_key(id)) {
+            match graphql::sortable_names(&client, assignment.id).await {
+                Ok(found) => {
+                    for (id, name) in found {
+                        names.entry(id).or_insert(name);
+                    }
+                }
+                Err(e) => println!("{} {}, fetching names one by one", "warning:".yellow(), e),
+            }
+        }
+
+        let missing: Vec<_> = user_ids
+            .iter()
+            .filter(|id| !names.contains_key(id))
+            .copied()
+            .collect();
+
+        let user_profiles = futures::stream::iter(missing.iter())
+            .map(|&id| fetch_user_profile(&canvas, id))
+            .buffered(PROFILE_REQUESTS)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+
+        names.extend(
+            missing
+                .into_iter()
+                .zip(user_profiles)
+                .map(|(id, profile)| (id, profile.sortable_name)),
+        );
+
+        let mut user_submissions: Vec<_> = submissions
+            .into_iter()
+            .map(|submission| {
+                let user_id = submission.user_id.unwrap();
+
+                Some(UserSubmission {
+                    user_id,
+                    sortable_name: names.remove(&user_id).unwrap_or_default(),
+                    due_at: due_dates.for_user(user_id),
+                    group: groups.remove(&user_id),
+                    attempt: None,
+                    submission,
+                })
+            })
+            .collect();
+
+        user_submissions.sort_by(|a, b| {
+            a.as_ref()
+                .unwrap()
+                .sortable_name
+                .cmp(&b.as_ref().unwrap().sortable_name)
+        });
+
+        let mut user_submissions: Vec<_> = match (&mine, cli.contiguous) {
+            (Some(mine), _) => user_submissions
+                .into_iter()
+                .filter(|s| mine.contains(&s.as_ref().unwrap().user_id))
+                .collect(),
+            (None, true) => user_submissions.drain(start..end).collect(),
+            (None, false) => user_submissions
+                .into_iter()
+                .filter(|s| portion_of(s.as_ref().unwrap().user_id, division_count) == portion)
+                .collect(),
+        };
+
+        let claimed = user_submissions.len();
+        user_submissions.retain(|s| {
+            claims::claimed_by_other(claims_dir, assignment.id, s.as_ref().unwrap().user_id)
+                .is_none()
+        });
+
+        if user_submissions.len() < claimed {
+            println!(
+                "{} students claimed by other graders are left out",
+                claimed - user_submissions.len()
+            );
+        }
+
+        // a regrade request is for one student, found by name
+        if cli.regrade {
+            if user_submissions.is_empty() {
+                return Err(format!("no submissions for {}", assignment_name).into());
+            }
+
+            let chosen = FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Student to regrade")
+                .items(
+                    &user_submissions
+                        .iter()
+                        .flatten()
+                        .map(|s| s.display_name(&roster))
+                        .collect::<Vec<_>>(),
+                )
+                .interact()?;
+
+            user_submissions = vec![user_submissions.swap_remove(chosen)];
+        }
+
+        for s in user_submissions.iter().flatten() {
+            progress.add(assignment.id, s.user_id);
+        }
+
+        match queue_order {
+            QueueOrder::Alphabetical => {}
+            QueueOrder::SubmissionTime => user_submissions.sort_by_key(|s| {
+                let submitted_at = s.as_ref().unwrap().submitted_at();
+
+                (submitted_at.is_none(), submitted_at)
+            }),
+            // students without a score last
+            QueueOrder::PreviousScore => user_submissions.sort_by(|a, b| {
+                let score = |s: &Option<UserSubmission>| {
+                    previous_scores
+                        .get(&s.as_ref().unwrap().user_id)
+                        .copied()
+                        .unwrap_or(f64::INFINITY)
+                };
+
+                score(a).total_cmp(&score(b))
+            }),
+            // late by an unknown amount after the known ones, on time last
+            QueueOrder::Lateness => user_submissions.sort_by_key(|s| {
+                std::cmp::Reverse(match s.as_ref().unwrap().lateness() {
+                    Lateness::Late(by) => (2, Some(by)),
+                    Lateness::Marked => (1, None),
+                    _ => (0, None),
+                })
+            }),
+            QueueOrder::Random => shuffle(&mut user_submissions, progress.seed.unwrap_or_default()),
+        }
+
+        let selections = match cli.regrade {
+            true => vec![0],
+            false => MultiSelect::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Users to grade for {}", assignment_name))
+                .items(
+                    &user_submissions
+                        .iter()
+                        .map(|s| {
+                            let s = s.as_ref().unwrap();
+                            let name = match s.submitted_at() {
+                                Some(_) => s.display_name(&roster).normal(),
+                                None => s.display_name(&roster).dimmed(),
+                            };
+
+                            match excused(s.user_id) {
+                                true => format!("{} ({})", name, "excused".cyan()),
+                                false => format!("{} ({})", name, s.lateness()),
+                            }
+                        })
+                        .collect::<Box<_>>(),
+                )
+                // those graded before resuming are left out
+                .defaults(
+                    &user_submissions
+                        .iter()
+                        .flatten()
+                        .map(|s| {
+                            progress.status(assignment.id, s.user_id)
+                                != Some(resume::Status::Graded)
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .interact()?,
+        };
+
+        let mut session = Session {
+            history: history.clone(),
+            guard: guard::Guard::new(&students).await?,
+            students: students.iter().map(|s| (s.id, s.clone())).collect(),
+            template: config::read(assignment_dir.as_deref(), config::COMMENT_TEMPLATE).await,
+            deductions: config::read(assignment_dir.as_deref(), config::DEDUCTIONS)
+                .await
+                .map(|d| deductions::parse(&d))
+                .unwrap_or_default(),
+            snippets: config::read(assignment_dir.as_deref(), config::SNIPPETS)
+                .await
+                .map(|s| snippets::parse(&s))
+                .unwrap_or_default(),
+            settings: config::settings(assignment_dir.as_deref()).await,
+            scripts: assignment_dir
+                .as_deref()
+                .map(|d| scripts::load(&d.join(config::SCRIPTS)))
+                .unwrap_or_default(),
+            rubric: client
+                .get_rubric(course.id, assignment.id)
+                .await
+                .unwrap_or_default(),
+            checks: checks::load(assignment_dir.as_deref(), options.build_by_default).await,
+            check_results: HashMap::new(),
+            late_policy: client.get_late_policy(course.id).await.ok(),
+            points_possible: assignment.points_possible,
+            roster: roster.clone(),
+            duplicates: HashMap::new(),
+            similar: HashMap::new(),
+            assignment_dir,
+            speed_grader: Some(client.speed_grader_url(course.id, assignment.id)),
+            existing,
+            graders: graders.clone(),
+            moderated: summary(assignment.id).is_some_and(|s| s.moderated_grading),
+            peer_reviews: HashMap::new(),
+            record: options.record,
+            sandbox: options.sandbox,
+        };
+
+        if summary(assignment.id).is_some_and(|s| s.peer_reviews) {
+            for review in client.get_peer_reviews(course.id, assignment.id).await? {
+                session
+                    .peer_reviews
+                    .entry(review.user_id)
+                    .or_default()
+                    .push(review);
+            }
+        }
+
+        println!("Downloading submissions...");
+
+        let mut downloaded = vec![];
+
+        for s in selections {
+            let mut submission = None;
+            std::mem::swap(&mut user_submissions[s], &mut submission);
+
+            let mut submission = submission.ok_or(Errors::InvalidSelection)?;
+            let name = submission.display_name(&session.roster);
+            let path = workspace.join(&name);
+
+            if cli.attempts {
+                submission.attempt =
+                    choose_attempt(&client, course.id, assignment.id, &submission, &name).await?;
+            }
+
+            let files = match &submission.attempt {
+                Some(attempt) => attempt.attachments.clone(),
+                None => session
+                    .existing
+                    .get(&submission.user_id)
+                    .map(|e| e.attachments.clone())
+                    .unwrap_or_default(),
+            };
+
+            if !files.is_empty() {
+                println!("{}:", name.bright_blue());
+                attachments::describe(&files);
+            }
+
+            if submission.submitted_at().is_none() {
+                println!("{} has no submission", name.bright_blue());
+                record_missing(&client, course.id, assignment, &session, &submission).await?;
+                continue;
+            }
+
+            let submission = match submission
+                .download_submission(&client, course.id, assignment.id, name.clone(), path)
+                .await
+            {
+                Ok(submission) => submission,
+                Err(e)
+                    if matches!(e.downcast_ref::<Errors>(), Some(Errors::AttachmentNotFound)) =>
+                {
+                    println!(
+                        "{} nothing to download for {}, skipping",
+                        "warning:".yellow(),
+                        name
+                    );
+                    continue;
+                }
+                Err(e)
+                    if matches!(
+                        e.downcast_ref::<Errors>(),
+                        Some(Errors::CloneFailed | Errors::NoCommitBeforeSubmission)
+                    ) =>
+                {
+                    println!(
+                        "{} couldn't check out the repository of {} ({}), skipping",
+                        "warning:".yellow(),
+                        name,
+                        e
+                    );
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            if cli.comment_files {
+                download_comment_files(&session, &submission).await?;
+            }
+
+            downloaded.push(submission);
+        }
+
+        flag_duplicates(&mut session, &downloaded, &cli.archive)?;
+        file_checks::print_missing(&session.settings, &downloaded);
+
+        if cli.moss && !downloaded.is_empty() {
+            match moss::submit(
+                assignment.name.as_deref().unwrap_or_default(),
+                session.assignment_dir.as_deref(),
+                &downloaded,
+            )
+            .await
+            {
+                Ok(url) => println!("MOSS results: {}", url.bright_blue()),
+                Err(e) => println!("{} MOSS: {}", "warning:".yellow(), e),
+            }
+        }
+
+        run_checks(&mut session, &downloaded).await?;
+
+        graded.push((*assignment, session, downloaded));
+    }
+
+    if !cli.regrade {
+        progress.save().await?;
+    }
+
+    // (assignment, submission) indices in grading order
+    let mut order: Vec<_> = graded
+        .iter()
+        .enumerate()
+        .flat_map(|(a, (_, _, downloaded))| (0..downloaded.len()).map(move |d| (a, d)))
+        .collect();
+
+    if graded.len() > 1
+        && Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Order")
+            .items(&[
+                "One assignment at a time",
+                "Each student's assignments together",
+            ])
+            .default(0)
+            .interact()?
+            == 1
+    {
+        // stable, so each student's assignments stay in the order selected
+        order.sort_by(|&(a, d), &(b, e)| graded[a].2[d].name.cmp(&graded[b].2[e].name));
+    }
+
+    // whether each assignment's grades were already held before
+    let mut held = vec![];
+    if cli.hold_grades {
+        for (assignment, _, _) in &graded {
+            held.push(post_policy::hold(&client, assignment.id).await?);
+        }
+
+        println!(
+            "{} grades will stay hidden until released, including those other graders post meanwhile",
+            "warning:".yellow()
+        );
+    }
+
+    let mut report = vec![];
+
+    // students given a grade, per assignment, to release when holding them
+    let mut posted_to: Vec<Vec<usize>> = vec![vec![]; graded.len()];
+
+    let mut queue = VecDeque::from(order);
+
+    // the post policies held above are put back even when grading stops at an
+    // error
+    let grading = async {
+        while let Some((a, d)) = queue.pop_front() {
+            let (assignment, session, downloaded) = &graded[a];
+
+            let claim = match claims::claim(claims_dir, assignment.id, downloaded[d].user_id) {
+                Ok(claim) => claim,
+                Err(owner) => {
+                    println!("{} was claimed by {}, skipping", downloaded[d].name, owner);
+                    continue;
+                }
+            };
+
+            let outcome = match downloaded[d].grade(session).await? {
+                Graded::Done(outcome) => outcome,
+                Graded::Deferred => {
+                    claim.release();
+                    println!("{} moved to the end of the queue", downloaded[d].name);
+                    queue.push_back((a, d));
+                    continue;
+                }
+                Graded::Exit => {
+                    claim.release();
+                    break;
+                }
+            };
+            let posted = post_grade(
+                &client,
+                course.id,
+                assignment,
+                session,
+                &downloaded[d],
+                &outcome,
+            )
             .await?;
 
-        d.grade().await?;
+            let members = match &downloaded[d].group {
+                Some(group) => group.members.clone(),
+                None => vec![downloaded[d].user_id],
+            };
+
+            if posted.is_some() {
+                // keeps canvas's unread indicators in step with what has been
+                // graded, a skipped submission stays unread for whoever grades it
+                for &user_id in &members {
+                    if let Err(e) = client.mark_read(course.id, assignment.id, user_id).await {
+                        println!(
+                            "{} couldn't mark the submission read: {}",
+                            "warning:".yellow(),
+                            e
+                        );
+                    }
+                }
+
+                posted_to[a].extend(&members);
+                claim.keep();
+            } else {
+                claim.release();
+            }
+
+            // every member, so the export has a row for each of them
+            for &user_id in &members {
+                progress.set(
+                    assignment.id,
+                    user_id,
+                    match posted {
+                        Some(_) => resume::Status::Graded,
+                        None => resume::Status::Skipped,
+                    },
+                    posted.or(outcome.score),
+                    outcome.comment.clone(),
+                    outcome.note.clone(),
+                );
+            }
+            if !cli.regrade {
+                progress.save().await?;
+            }
+
+            report.push((
+                downloaded[d].name.as_str(),
+                assignment.name.as_deref().unwrap_or_default(),
+                posted,
+            ));
+        }
+
+        Ok::<_, Box<dyn std::error::Error>>(())
+    }
+    .await;
+
+    if let Err(e) = grading {
+        for ((assignment, _, _), &held) in graded.iter().zip(&held) {
+            restore_policy(&client, assignment, held).await;
+        }
+
+        return Err(e);
+    }
+
+    if graded.len() > 1 {
+        print_report(&report);
+    }
+
+    if !cli.regrade {
+        resume::print_path();
+    }
+
+    for (((assignment, _, _), students), held) in graded.iter().zip(&posted_to).zip(held) {
+        if !students.is_empty() {
+            if let Err(e) = offer_release(&client, assignment, students).await {
+                println!(
+                    "{} couldn't release the grades posted: {}",
+                    "warning:".yellow(),
+                    e
+                );
+            }
+        }
+
+        restore_policy(&client, assignment, held).await;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn portions_are_stable_and_in_range() {
+        for user_id in 0..1000 {
+            let portion = portion_of(user_id, 4);
+            assert!(portion < 4);
+            assert_eq!(portion, portion_of(user_id, 4));
+            assert_eq!(portion_of(user_id, 1), 0);
+        }
+    }
+
+    #[test]
+    fn portions_are_spread_out() {
+        let mut counts = [0; 4];
+        for user_id in 1000..2000 {
+            counts[portion_of(user_id, 4)] += 1;
+        }
+
+        assert!(counts.iter().all(|&n| n > 150), "{counts:?}");
+    }
+
+    #[test]
+    fn shuffle_is_stable_for_a_seed() {
+        let mut first: Vec<_> = (0..20).collect();
+        let mut second = first.clone();
+        shuffle(&mut first, 42);
+        shuffle(&mut second, 42);
+        assert_eq!(first, second);
+
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn shuffle_depends_on_the_seed() {
+        let mut first: Vec<_> = (0..20).collect();
+        let mut second = first.clone();
+        shuffle(&mut first, 1);
+        shuffle(&mut second, 2);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn html_to_text_keeps_lines_and_decodes_entities() {
+        assert_eq!(
+            html_to_text("<p>a &amp;lt; b</p><p>x<BR/>y</p>"),
+            "a &lt; b\nx\ny\n"
+        );
+        assert_eq!(html_to_text("1&nbsp;&gt;&nbsp;0"), "1 > 0");
+    }
+}
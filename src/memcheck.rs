@@ -0,0 +1,399 @@
+//! Memory checks of the student's program on each test input, enabled with
+//! `memcheck = "valgrind"` or `memcheck = "asan"` in `grader.toml`. They pass
+//! when there are no invalid accesses, uses of uninitialised memory or
+//! definite leaks, worth `memcheck_points` each (default 0), and their output
+//! is a one line summary after the first error. Both tools report to files,
+//! one per process, so a program printing a lot can't push its report out of
+//! the truncated output.
+//!
+//! With `asan` the build has to use `-fsanitize=address`, e.g. a `build.txt`
+//! of `make CFLAGS='-g -fsanitize=address' LDFLAGS=-fsanitize=address`. A
+//! program built without it fails the check rather than passing unchecked.
+
+use crate::checks::{self, Check, Kind};
+use crate::{config, harness, sandbox};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Prefix of the memory checks' names.
+pub const PREFIX: &str = "memcheck ";
+
+const VALGRIND: &str =
+    "valgrind --trace-children=yes --leak-check=full --show-leak-kinds=definite --num-callers=8";
+
+/// Leak detection is only on by default on some platforms, and verbosity
+/// makes the runtime announce itself, so an uninstrumented program shows.
+const ASAN_OPTIONS: &str = "detect_leaks=1:verbosity=1";
+
+/// Lines of the first error kept before the summary.
+const ERROR_LINES: usize = 4;
+
+/// valgrind's error headers, and how they are summarized.
+const VALGRIND_ERRORS: &[(&str, &str)] = &[
+    ("Invalid read", "invalid read"),
+    ("Invalid write", "invalid write"),
+    ("Invalid free", "invalid free"),
+    ("Mismatched free", "mismatched free"),
+    (
+        "Conditional jump or move depends on uninitialised",
+        "uninitialised value",
+    ),
+    ("Use of uninitialised value", "uninitialised value"),
+    ("Syscall param", "uninitialised value"),
+    ("Source and destination overlap", "overlapping copy"),
+];
+
+/// `==123== Invalid read of size 4`
+static VALGRIND_LINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^==\d+== (.*)$").unwrap());
+
+static VALGRIND_LEAK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"definitely lost: ([\d,]+) bytes in ([\d,]+) blocks").unwrap());
+
+/// `ERROR: AddressSanitizer: heap-buffer-overflow on address ...`, or
+/// UndefinedBehaviorSanitizer's `file.c:3:5: runtime error: ...`
+static ASAN_ERROR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"ERROR: AddressSanitizer: ([\w-]+)|(runtime error): ").unwrap());
+
+static ASAN_LEAK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"SUMMARY: AddressSanitizer: (\d+) byte\(s\) leaked in (\d+) allocation\(s\)")
+        .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tool {
+    Valgrind,
+    Asan,
+}
+
+#[derive(Default)]
+struct Summary {
+    /// error kinds and how many times they happened
+    errors: BTreeMap<&'static str, usize>,
+    /// (bytes, blocks)
+    leaked: (u64, u64),
+    first: Vec<String>,
+}
+
+impl Summary {
+    fn clean(&self) -> bool {
+        self.errors.is_empty() && self.leaked.0 == 0
+    }
+
+    fn error(&mut self, kind: &'static str, line: &str) {
+        *self.errors.entry(kind).or_default() += 1;
+
+        if self.first.is_empty() {
+            self.first.push(line.trim().to_string());
+        }
+    }
+
+    fn line(&self) -> String {
+        if self.clean() {
+            return "no memory errors or leaks".into();
+        }
+
+        let mut parts: Vec<_> = self
+            .errors
+            .iter()
+            .map(|(kind, n)| format!("{kind} x{n}"))
+            .collect();
+
+        if self.leaked.0 > 0 {
+            parts.push(format!(
+                "{} bytes leaked in {} blocks",
+                self.leaked.0, self.leaked.1
+            ));
+        }
+
+        parts.join(", ")
+    }
+}
+
+fn number(s: &str) -> u64 {
+    s.replace(',', "").parse().unwrap_or(0)
+}
+
+fn valgrind(output: &str) -> Option<Summary> {
+    let mut summary = Summary::default();
+    let mut found = false;
+    let mut in_first = false;
+
+    for line in output.lines() {
+        let Some(caps) = VALGRIND_LINE.captures(line) else {
+            continue;
+        };
+        found = true;
+        let text = &caps[1];
+
+        if let Some(caps) = VALGRIND_LEAK.captures(text) {
+            summary.leaked.0 += number(&caps[1]);
+            summary.leaked.1 += number(&caps[2]);
+            continue;
+        }
+
+        match VALGRIND_ERRORS.iter().find(|(h, _)| text.starts_with(h)) {
+            Some((_, kind)) => {
+                in_first = summary.first.is_empty();
+                summary.error(*kind, text);
+            }
+            None if in_first && text.trim().is_empty() => in_first = false,
+            None if in_first && summary.first.len() < ERROR_LINES => {
+                summary.first.push(text.trim().to_string())
+            }
+            None => {}
+        }
+    }
+
+    found.then_some(summary)
+}
+
+fn asan(output: &str) -> Summary {
+    let mut summary = Summary::default();
+    let mut in_first = false;
+
+    for line in output.lines() {
+        if let Some(caps) = ASAN_LEAK.captures(line) {
+            summary.leaked.0 += number(&caps[1]);
+            summary.leaked.1 += number(&caps[2]);
+        } else if let Some(caps) = ASAN_ERROR.captures(line) {
+            let kind = match caps.get(1).map(|m| m.as_str()) {
+                Some("heap-buffer-overflow") => "heap buffer overflow",
+                Some("stack-buffer-overflow") => "stack buffer overflow",
+                Some("global-buffer-overflow") => "global buffer overflow",
+                Some("heap-use-after-free") => "use after free",
+                Some("double-free") => "double free",
+                Some("attempting") => "invalid free",
+                Some("SEGV") => "segfault",
+                Some(_) => "address error",
+                None => "undefined behavior",
+            };
+
+            in_first = summary.first.is_empty();
+            summary.error(kind, line);
+        } else if in_first && line.trim_start().starts_with('#') {
+            match summary.first.len() < ERROR_LINES {
+                true => summary.first.push(line.trim().to_string()),
+                false => in_first = false,
+            }
+        }
+    }
+
+    summary
+}
+
+/// The reports the tool wrote to `<prefix>.<pid>`, removing them.
+async fn reports(prefix: &Path) -> String {
+    let (Some(dir), Some(name)) = (prefix.parent(), prefix.file_name()) else {
+        return String::new();
+    };
+    let name = format!("{}.", name.to_string_lossy());
+
+    let mut text = String::new();
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return text;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if !entry.file_name().to_string_lossy().starts_with(&name) {
+            continue;
+        }
+
+        if let Ok(report) = fs::read_to_string(entry.path()).await {
+            text.push_str(&report);
+        }
+        let _ = fs::remove_file(entry.path()).await;
+    }
+
+    text
+}
+
+/// A memory check per test, when the assignment has them.
+pub fn load(assignment_dir: Option<&Path>, settings: &config::Settings) -> Vec<Check> {
+    let (Some(tool), Some(program)) = (settings.memcheck, &settings.test_command) else {
+        return vec![];
+    };
+
+    harness::inputs(assignment_dir)
+        .into_iter()
+        .map(|input| Check {
+            points: settings.memcheck_points,
+            name: format!("{PREFIX}{}", harness::name(&input)),
+            command: format!(
+                "{} < {} > /dev/null",
+                program,
+                sandbox::quote(&input.to_string_lossy())
+            ),
+            kind: Kind::Memcheck(tool),
+            mounts: input.parent().map(Path::to_path_buf).into_iter().collect(),
+        })
+        .collect()
+}
+
+pub async fn run(
+    command: &str,
+    tool: Tool,
+    dir: &Path,
+    mounts: &[PathBuf],
+    backend: sandbox::Backend,
+) -> Result<(bool, String), Box<dyn std::error::Error>> {
+    let report = checks::report_path(
+        dir,
+        match tool {
+            Tool::Valgrind => "valgrind",
+            Tool::Asan => "asan",
+        },
+    );
+
+    let wrapped = match tool {
+        Tool::Valgrind => format!(
+            "{} --log-file={}.%p sh -c {}",
+            VALGRIND,
+            sandbox::quote(&report.to_string_lossy()),
+            sandbox::quote(command)
+        ),
+        Tool::Asan => format!(
+            "ASAN_OPTIONS={}; export ASAN_OPTIONS; {}",
+            sandbox::quote(&format!("{ASAN_OPTIONS}:log_path={}", report.display())),
+            command
+        ),
+    };
+
+    let (_, output) = checks::execute(&wrapped, dir, mounts, backend).await?;
+    let reports = reports(&report).await;
+
+    let summary = match tool {
+        Tool::Valgrind => match valgrind(&reports) {
+            Some(summary) => summary,
+            // e.g. valgrind isn't installed, or it timed out
+            None => return Ok((false, output)),
+        },
+        Tool::Asan => {
+            if !reports.contains("AddressSanitizer") {
+                return Ok((
+                    false,
+                    format!("{output}\nnot built with -fsanitize=address, nothing was checked"),
+                ));
+            }
+
+            // undefined behavior is still reported on stderr
+            asan(&format!("{reports}\n{output}"))
+        }
+    };
+
+    let mut lines = summary.first.clone();
+    lines.push(summary.line());
+
+    Ok((summary.clean(), lines.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valgrind_clean() {
+        let output = [
+            "==7== Memcheck, a memory error detector",
+            "==7== All heap blocks were freed -- no leaks are possible",
+            "==7== ERROR SUMMARY: 0 errors from 0 contexts (suppressed: 0 from 0)",
+        ]
+        .join("\n");
+
+        let summary = valgrind(&output).unwrap();
+        assert!(summary.clean());
+        assert_eq!(summary.line(), "no memory errors or leaks");
+    }
+
+    #[test]
+    fn valgrind_errors_and_leaks() {
+        let output = [
+            "==7== Invalid read of size 4",
+            "==7==    at 0x109156: main (prog.c:5)",
+            "==7==  Address 0x4a4e068 is 0 bytes after a block of size 40 alloc'd",
+            "==7== ",
+            "==7== Invalid read of size 4",
+            "==7==    at 0x109160: main (prog.c:6)",
+            "==7== ",
+            "==7==    definitely lost: 1,024 bytes in 2 blocks",
+        ]
+        .join("\n");
+
+        let summary = valgrind(&output).unwrap();
+        assert_eq!(summary.errors["invalid read"], 2);
+        assert_eq!(summary.leaked, (1024, 2));
+        assert_eq!(
+            summary.first,
+            [
+                "Invalid read of size 4",
+                "at 0x109156: main (prog.c:5)",
+                "Address 0x4a4e068 is 0 bytes after a block of size 40 alloc'd",
+            ]
+        );
+        assert_eq!(
+            summary.line(),
+            "invalid read x2, 1024 bytes leaked in 2 blocks"
+        );
+    }
+
+    #[test]
+    fn valgrind_truncated() {
+        let output = [
+            "==7== Invalid write of size 1",
+            "==7==    at 0x10",
+            "[output truncated, 65536 of 1048576 bytes shown]",
+        ]
+        .join("\n");
+
+        let summary = valgrind(&output).unwrap();
+        assert_eq!(summary.errors["invalid write"], 1);
+        assert!(!summary.clean());
+    }
+
+    #[test]
+    fn valgrind_missing() {
+        assert!(valgrind("").is_none());
+        assert!(valgrind("sh: 1: valgrind: not found").is_none());
+    }
+
+    #[test]
+    fn asan_error() {
+        let output = [
+            "==9==ERROR: AddressSanitizer: heap-buffer-overflow on address 0x602000000018",
+            "READ of size 4 at 0x602000000018 thread T0",
+            "    #0 0x4011d6 in main prog.c:5",
+            "    #1 0x7f2a1c0 in __libc_start_main",
+            "SUMMARY: AddressSanitizer: heap-buffer-overflow prog.c:5 in main",
+        ]
+        .join("\n");
+
+        let summary = asan(&output);
+        assert_eq!(summary.errors["heap buffer overflow"], 1);
+        assert_eq!(summary.errors.len(), 1);
+        assert_eq!(summary.first.len(), 3);
+        assert_eq!(summary.first[1], "#0 0x4011d6 in main prog.c:5");
+    }
+
+    #[test]
+    fn asan_leaks_and_undefined_behavior() {
+        let output = [
+            "prog.c:3:5: runtime error: signed integer overflow",
+            "==9==ERROR: LeakSanitizer: detected memory leaks",
+            "SUMMARY: AddressSanitizer: 24 byte(s) leaked in 2 allocation(s).",
+        ]
+        .join("\n");
+
+        let summary = asan(&output);
+        assert_eq!(summary.errors["undefined behavior"], 1);
+        assert_eq!(summary.leaked, (24, 2));
+    }
+
+    #[test]
+    fn asan_clean() {
+        assert!(asan("==9==AddressSanitizer: libc interceptors initialized").clean());
+    }
+}
@@ -0,0 +1,90 @@
+//! Memory limited checks, e.g. `5 small <= 64M: ./prog < input.txt`, pass
+//! when the command succeeds and its peak resident set size (the largest of
+//! any process it started) stays within the limit. Measured with GNU time,
+//! which reports to a file so a program printing a lot can't push the
+//! measurement out of its truncated output.
+
+use crate::bench::TIME;
+use crate::{checks, sandbox};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+fn format_kib(kib: u64) -> String {
+    match kib {
+        0..=1023 => format!("{kib} KiB"),
+        _ => format!("{:.1} MiB", kib as f64 / 1024.0),
+    }
+}
+
+/// The peak in KiB from GNU time's report, its last line after e.g.
+/// `Command exited with non-zero status 1`.
+fn peak_kib(report: &str) -> Option<u64> {
+    report.lines().last()?.trim().parse().ok()
+}
+
+pub async fn run(
+    command: &str,
+    limit_kib: u64,
+    dir: &Path,
+    mounts: &[PathBuf],
+    backend: sandbox::Backend,
+) -> Result<(bool, String), Box<dyn std::error::Error>> {
+    let report = checks::report_path(dir, "time");
+    let wrapped = format!(
+        "{} -o {} -f '%M' sh -c {}",
+        TIME,
+        sandbox::quote(&report.to_string_lossy()),
+        sandbox::quote(command)
+    );
+
+    let (passed, mut output) = checks::execute(&wrapped, dir, mounts, backend).await?;
+
+    let peak = fs::read_to_string(&report)
+        .await
+        .ok()
+        .and_then(|r| peak_kib(&r));
+    let _ = fs::remove_file(&report).await;
+
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+
+    let Some(peak) = peak else {
+        output.push_str("peak memory unknown, is GNU time installed?");
+        return Ok((false, output));
+    };
+
+    output.push_str(&format!(
+        "peak {}, limit {}",
+        format_kib(peak),
+        format_kib(limit_kib)
+    ));
+
+    Ok((passed && peak <= limit_kib, output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peak_is_the_last_line() {
+        assert_eq!(peak_kib("2048\n"), Some(2048));
+        assert_eq!(
+            peak_kib("Command exited with non-zero status 1\n2048\n"),
+            Some(2048)
+        );
+    }
+
+    #[test]
+    fn no_peak_without_a_number() {
+        assert_eq!(peak_kib(""), None);
+        assert_eq!(peak_kib("Command terminated by signal 9\n"), None);
+    }
+
+    #[test]
+    fn formats_kib_and_mib() {
+        assert_eq!(format_kib(512), "512 KiB");
+        assert_eq!(format_kib(1536), "1.5 MiB");
+    }
+}
@@ -0,0 +1,124 @@
+//! Submits the `.c` and `.h` files of every downloaded submission to
+//! [MOSS](https://theory.stanford.edu/~aiken/moss/) with `--moss`, the same
+//! way the `moss` perl script does, and prints the results url. Needs a MOSS
+//! user id in `GRADER_MOSS_ID`. The assignment's `starter/` files are sent as
+//! base files, so code handed out doesn't count as a match.
+
+use crate::{config, DownloadedSubmission};
+use std::env;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use walkdir::WalkDir;
+
+const SERVER: &str = "moss.stanford.edu:7690";
+
+/// Matches shown per pair of submissions.
+const MAX_MATCHES: usize = 10;
+
+/// Pairs of submissions in the results.
+const SHOWN: usize = 250;
+
+/// The C files under `dir`.
+fn sources(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<_> = WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| p.extension().is_some_and(|e| e == "c" || e == "h"))
+        .collect();
+
+    files.sort();
+    files
+}
+
+/// Sends one file as `id`, 0 for base files. MOSS splits on spaces, so
+/// they're replaced in the name.
+async fn upload(
+    stream: &mut TcpStream,
+    id: usize,
+    name: &str,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = tokio::fs::read(path).await?;
+    let header = format!(
+        "file {} c {} {}\n",
+        id,
+        contents.len(),
+        name.replace(' ', "_")
+    );
+
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&contents).await?;
+
+    Ok(())
+}
+
+/// Uploads the submissions, one directory per student, returning the
+/// results url.
+pub async fn submit(
+    assignment: &str,
+    assignment_dir: Option<&Path>,
+    submissions: &[DownloadedSubmission],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let user_id = env::var("GRADER_MOSS_ID").map_err(|_| "GRADER_MOSS_ID is not set")?;
+
+    let mut stream = TcpStream::connect(SERVER).await?;
+
+    let options = format!(
+        "moss {user_id}\ndirectory 1\nX 0\nmaxmatches {MAX_MATCHES}\nshow {SHOWN}\nlanguage c\n"
+    );
+    stream.write_all(options.as_bytes()).await?;
+
+    // nothing else is sent until the files, so nothing is lost buffering
+    let mut answer = String::new();
+    BufReader::new(&mut stream).read_line(&mut answer).await?;
+    if answer.trim() != "yes" {
+        return Err("MOSS didn't accept the request, is GRADER_MOSS_ID right?".into());
+    }
+
+    if let Some(starter) = assignment_dir.map(|d| d.join(config::STARTER)) {
+        for path in sources(&starter) {
+            let name = path.strip_prefix(&starter)?.display().to_string();
+            upload(&mut stream, 0, &format!("starter/{name}"), &path).await?;
+        }
+    }
+
+    let mut id = 1;
+
+    for s in submissions {
+        let student = s
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| s.user_id.to_string());
+
+        for path in sources(&s.path) {
+            let name = path.strip_prefix(&s.path)?.display().to_string();
+            upload(&mut stream, id, &format!("{student}/{name}"), &path).await?;
+            id += 1;
+        }
+    }
+
+    println!(
+        "Uploaded {} files from {} submissions to MOSS, waiting for the results...",
+        id - 1,
+        submissions.len()
+    );
+
+    stream
+        .write_all(format!("query 0 {assignment}\n").as_bytes())
+        .await?;
+
+    let mut url = String::new();
+    BufReader::new(&mut stream).read_line(&mut url).await?;
+
+    stream.write_all(b"end\n").await?;
+
+    match url.trim() {
+        url if url.starts_with("http") => Ok(url.to_string()),
+        other => Err(format!("MOSS didn't return a url: {other}").into()),
+    }
+}
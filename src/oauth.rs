@@ -0,0 +1,318 @@
+//! Logs in through the browser with canvas's OAuth2 flow, for profiles with a
+//! developer key (`client_id` and `client_secret`) instead of an access
+//! token. The tokens are kept in the system keyring, or without one in
+//! `~/.config/grader/tokens.json` readable only by the grader, and refreshed
+//! when they expire or canvas rejects them.
+//!
+//! The developer key's redirect URI must be `http://localhost:8910/` (or the
+//! port in `GRADER_OAUTH_PORT`).
+
+use crate::profiles::Profile;
+use crate::{browser, retry, secrets};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+const PORT: u16 = 8910;
+
+/// Refresh this long before canvas says the token expires.
+const MARGIN: i64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Token {
+    access_token: String,
+    refresh_token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    /// only sent with the first token, refreshing keeps the old one
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+}
+
+fn path() -> PathBuf {
+    PathBuf::from(env::var("HOME").unwrap_or_default()).join(".config/grader/tokens.json")
+}
+
+fn port() -> u16 {
+    env::var("GRADER_OAUTH_PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(PORT)
+}
+
+fn redirect_uri() -> String {
+    format!("http://localhost:{}/", port())
+}
+
+/// Tokens in the fallback file, by canvas base url.
+async fn load_file() -> HashMap<String, Token> {
+    match fs::read(path()).await {
+        Ok(contents) => serde_json::from_slice(&contents).unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    }
+}
+
+async fn load(base_url: &str) -> Option<Token> {
+    match secrets::oauth_tokens(base_url) {
+        Some(json) => serde_json::from_str(&json).ok(),
+        None => load_file().await.remove(base_url),
+    }
+}
+
+async fn store(base_url: &str, token: &Token) -> Result<(), Box<dyn std::error::Error>> {
+    if secrets::save_oauth_tokens(base_url, &serde_json::to_string(token)?).is_ok() {
+        return Ok(());
+    }
+
+    // no keyring, e.g. over ssh, so a file only the grader can read
+    let mut tokens = load_file().await;
+    tokens.insert(base_url.to_string(), token.clone());
+
+    let path = path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).await?;
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .await?;
+    // the mode only applies to a new file
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))
+        .await?;
+    file.write_all(&serde_json::to_vec_pretty(&tokens)?).await?;
+
+    Ok(())
+}
+
+/// Exchanges an authorization code or refresh token for a new token.
+async fn request(
+    profile: &Profile,
+    client_id: &str,
+    client_secret: &str,
+    grant: &[(&str, &str)],
+    refresh_token: Option<&str>,
+) -> Result<Token, Box<dyn std::error::Error>> {
+    let redirect_uri = redirect_uri();
+
+    let mut form = vec![
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+        ("redirect_uri", redirect_uri.as_str()),
+    ];
+    form.extend_from_slice(grant);
+
+    let response: TokenResponse = retry::send(
+        reqwest::Client::new()
+            .post(format!("{}login/oauth2/token", profile.base_url))
+            .form(&form),
+    )
+    .await?
+    .json()
+    .await?;
+
+    Ok(Token {
+        access_token: response.access_token,
+        refresh_token: response
+            .refresh_token
+            .or(refresh_token.map(String::from))
+            .ok_or("canvas sent no refresh token")?,
+        expires_at: Utc::now() + Duration::seconds(response.expires_in.unwrap_or(3600) - MARGIN),
+    })
+}
+
+/// Opens canvas's login page and waits for it to redirect back with the
+/// authorization code.
+async fn authorize(
+    client_id: &str,
+    profile: &Profile,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(("127.0.0.1", port())).await?;
+
+    // only needs to tell this login's redirect apart from a stale one
+    let state = SystemTime::now()
+        .duration_since(UNIX_EPOCH)?
+        .as_nanos()
+        .to_string();
+
+    let url = reqwest::Url::parse_with_params(
+        &format!("{}login/oauth2/auth", profile.base_url),
+        &[
+            ("client_id", client_id),
+            ("response_type", "code"),
+            ("redirect_uri", redirect_uri().as_str()),
+            ("state", state.as_str()),
+        ],
+    )?;
+
+    println!("Log in to canvas in the browser");
+    browser::open(url.as_str());
+
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+
+        // e.g. `GET /?code=...&state=... HTTP/1.1`
+        let mut line = String::new();
+        BufReader::new(&mut stream).read_line(&mut line).await?;
+
+        let target = line.split_whitespace().nth(1).unwrap_or("/");
+        let query: HashMap<_, _> = reqwest::Url::parse(&format!("http://localhost{target}"))?
+            .query_pairs()
+            .into_owned()
+            .collect();
+
+        // ignore the browser asking for a favicon
+        if query.get("state") != Some(&state) {
+            stream.write_all(b"HTTP/1.1 404 Not Found\r\n\r\n").await?;
+            continue;
+        }
+
+        let body = match query.get("code") {
+            Some(_) => "Logged in, you can close this tab.",
+            None => "Login failed, see the grader for details.",
+        };
+        stream
+            .write_all(
+                format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n{body}").as_bytes(),
+            )
+            .await?;
+
+        return match query.get("code") {
+            Some(code) => Ok(code.clone()),
+            None => Err(format!(
+                "canvas login failed: {}",
+                query.get("error").map(String::as_str).unwrap_or("no code")
+            )
+            .into()),
+        };
+    }
+}
+
+/// The profile's access token, logging in or refreshing the stored token as
+/// needed when it uses a developer key.
+pub async fn access_token(profile: &Profile) -> Result<String, Box<dyn std::error::Error>> {
+    match &profile.access_token {
+        Some(token) => Ok(token.clone()),
+        None => Ok(fetch(profile, false).await?.access_token),
+    }
+}
+
+/// The stored token, refreshed when it has expired or `force` is set, or a
+/// new one from logging in.
+async fn fetch(profile: &Profile, force: bool) -> Result<Token, Box<dyn std::error::Error>> {
+    let (Some(client_id), Some(client_secret)) = (&profile.client_id, &profile.client_secret)
+    else {
+        return Err("no access token or developer key for canvas".into());
+    };
+
+    let token = match load(&profile.base_url).await {
+        Some(token) if !force && token.expires_at > Utc::now() => return Ok(token),
+        Some(token) => {
+            let refreshed = request(
+                profile,
+                client_id,
+                client_secret,
+                &[
+                    ("grant_type", "refresh_token"),
+                    ("refresh_token", token.refresh_token.as_str()),
+                ],
+                Some(token.refresh_token.as_str()),
+            )
+            .await;
+
+            match refreshed {
+                Ok(token) => token,
+                // revoked or the developer key changed, log in again
+                Err(_) => login(profile, client_id, client_secret).await?,
+            }
+        }
+        None => login(profile, client_id, client_secret).await?,
+    };
+
+    store(&profile.base_url, &token).await?;
+
+    Ok(token)
+}
+
+/// Hands out the profile's access token for the whole run, refreshing it
+/// once it expires or when canvas rejects it. A profile's own access token
+/// is handed out as is.
+pub struct Tokens {
+    profile: Profile,
+    token: Mutex<Option<Token>>,
+}
+
+impl Tokens {
+    pub fn new(profile: &Profile) -> Self {
+        Self {
+            profile: profile.clone(),
+            token: Mutex::new(None),
+        }
+    }
+
+    pub async fn get(&self) -> Result<String, Box<dyn std::error::Error>> {
+        if let Some(token) = &self.profile.access_token {
+            return Ok(token.clone());
+        }
+
+        let mut token = self.token.lock().await;
+
+        match &*token {
+            Some(token) if token.expires_at > Utc::now() => Ok(token.access_token.clone()),
+            _ => {
+                let fetched = fetch(&self.profile, false).await?;
+                *token = Some(fetched.clone());
+                Ok(fetched.access_token)
+            }
+        }
+    }
+
+    /// Replaces the token canvas rejected, returning whether there is a new
+    /// one to try. Requests rejected together only refresh it once.
+    pub async fn refresh(&self, rejected: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        if self.profile.access_token.is_some() {
+            return Ok(false);
+        }
+
+        let mut token = self.token.lock().await;
+
+        if token.as_ref().is_some_and(|t| t.access_token != rejected) {
+            return Ok(true);
+        }
+
+        *token = Some(fetch(&self.profile, true).await?);
+        Ok(true)
+    }
+}
+
+async fn login(
+    profile: &Profile,
+    client_id: &str,
+    client_secret: &str,
+) -> Result<Token, Box<dyn std::error::Error>> {
+    let code = authorize(client_id, profile).await?;
+
+    request(
+        profile,
+        client_id,
+        client_secret,
+        &[("grant_type", "authorization_code"), ("code", &code)],
+        None,
+    )
+    .await
+}
@@ -0,0 +1,70 @@
+//! Quick look at a single student's submission, for when they show up at
+//! office hours asking about their grade.
+
+use crate::{
+    canvas, checks, config, download_student, inject, integrity, open_shell, roster, watch, Options,
+};
+use canvasapi::models::assignment::Assignment;
+use colored::Colorize;
+use std::path::PathBuf;
+
+pub async fn run(
+    client: &canvas::Client,
+    course_id: usize,
+    assignment: &Assignment,
+    student: &canvas::User,
+    roster: &roster::Roster,
+    watch_files: bool,
+    options: Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Fetching {}...", student.sortable_name.bright_blue());
+
+    let path = PathBuf::from(&student.sortable_name);
+
+    let Some(d) = download_student(client, course_id, assignment, student, roster, path).await?
+    else {
+        println!("{} has not submitted", student.sortable_name);
+        return Ok(());
+    };
+
+    println!("Submission is {}", d.lateness);
+
+    match client
+        .get_submission_grade(course_id, assignment.id, student.id)
+        .await?
+        .score
+    {
+        Some(score) => match assignment.points_possible {
+            Some(points) => println!("Current score {}/{}", score, points),
+            None => println!("Current score {}", score),
+        },
+        None => println!("Not graded yet"),
+    }
+
+    integrity::print_notes(&d.notes);
+
+    let assignment_dir = config::assignment_dir(
+        Some(assignment.id),
+        assignment.name.as_deref().unwrap_or_default(),
+    );
+    let checks = checks::load(assignment_dir.as_deref(), options.build_by_default).await;
+
+    let snapshot = inject::Snapshot::take(&d.path);
+    let injected = inject::inject(assignment_dir.as_deref(), &d.path).await?;
+    inject::print(&injected, &d.path);
+
+    let results = checks::run_all(&checks, &d.path, options.sandbox).await?;
+
+    if !results.is_empty() {
+        checks::print(&results);
+    }
+
+    match watch_files && !checks.is_empty() {
+        true => watch::run(&checks, &d.path, options.sandbox).await?,
+        false => open_shell(&d.path, None)?,
+    }
+
+    snapshot.restore(&injected).await?;
+
+    Ok(())
+}
@@ -0,0 +1,226 @@
+//! Per-member grade adjustment for group projects based on peer evaluations.
+//!
+//! The evaluations are read from a CSV with the columns
+//! `group,evaluator,evaluatee,score`, where students are identified by their
+//! canvas id, login id, email or name.
+
+use crate::{canvas, history};
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct Evaluation {
+    group: String,
+    evaluator: String,
+    evaluatee: String,
+    score: f64,
+}
+
+#[derive(Debug)]
+struct AdjustedGrade<'a> {
+    student: &'a canvas::User,
+    /// mean of the scores groupmates gave, if any evaluated them
+    received: Option<f64>,
+    factor: f64,
+    score: f64,
+}
+
+fn find_student<'a>(students: &'a [canvas::User], key: &str) -> Option<&'a canvas::User> {
+    let key = key.trim().to_lowercase();
+
+    students.iter().find(|s| {
+        s.id.to_string() == key
+            || s.name.to_lowercase() == key
+            || s.sortable_name.to_lowercase() == key
+            || s.login_id.as_deref().map(str::to_lowercase).as_deref() == Some(key.as_str())
+            || s.email.as_deref().map(str::to_lowercase).as_deref() == Some(key.as_str())
+    })
+}
+
+fn mean(scores: &[f64]) -> Option<f64> {
+    match scores.len() {
+        0 => None,
+        n => Some(scores.iter().sum::<f64>() / n as f64),
+    }
+}
+
+/// Scales the group grade by how each member was rated relative to the rest
+/// of their group. Self evaluations are ignored.
+fn adjust<'a>(
+    group_grade: f64,
+    points_possible: Option<f64>,
+    members: &BTreeMap<usize, (&'a canvas::User, Vec<f64>)>,
+) -> Vec<AdjustedGrade<'a>> {
+    let received: Vec<_> = members
+        .values()
+        .map(|(student, scores)| (*student, mean(scores)))
+        .collect();
+
+    let group_mean = mean(&received.iter().filter_map(|(_, r)| *r).collect::<Vec<_>>());
+
+    received
+        .into_iter()
+        .map(|(student, received)| {
+            let factor = match (received, group_mean) {
+                (Some(r), Some(m)) if m > 0.0 => r / m,
+                _ => 1.0,
+            };
+            let score = group_grade * factor;
+
+            AdjustedGrade {
+                student,
+                received,
+                factor,
+                score: points_possible.map_or(score, |max| score.min(max)),
+            }
+        })
+        .collect()
+}
+
+pub async fn run(
+    client: &canvas::Client,
+    course_id: usize,
+    assignment_id: usize,
+    assignment_name: &str,
+    points_possible: Option<f64>,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let evaluations = csv::Reader::from_path(path)?
+        .deserialize()
+        .collect::<Result<Vec<Evaluation>, _>>()?;
+
+    println!("Loading students...");
+
+    let students = client.get_course_students(course_id).await?;
+
+    let mut groups: BTreeMap<&str, BTreeMap<usize, (&canvas::User, Vec<f64>)>> = BTreeMap::new();
+
+    for e in &evaluations {
+        let (Some(evaluator), Some(evaluatee)) = (
+            find_student(&students, &e.evaluator),
+            find_student(&students, &e.evaluatee),
+        ) else {
+            println!(
+                "{} unknown student in evaluation of {} by {}, skipping",
+                "warning:".yellow(),
+                e.evaluatee,
+                e.evaluator
+            );
+            continue;
+        };
+
+        let members = groups.entry(e.group.as_str()).or_default();
+        members.entry(evaluator.id).or_insert((evaluator, vec![]));
+        let (_, received) = members.entry(evaluatee.id).or_insert((evaluatee, vec![]));
+
+        if evaluator.id != evaluatee.id {
+            received.push(e.score);
+        }
+    }
+
+    let theme = ColorfulTheme::default();
+
+    for (group, members) in &groups {
+        println!("Group {}", group.bright_blue());
+
+        let first = *members.keys().next().unwrap();
+        let current = client
+            .get_submission_grade(course_id, assignment_id, first)
+            .await?
+            .score;
+
+        let mut input = Input::<f64>::with_theme(&theme);
+        input.with_prompt("Group grade");
+        if let Some(score) = current {
+            input.default(score);
+        }
+        let group_grade = input.interact()?;
+
+        let grades = adjust(group_grade, points_possible, members);
+
+        for g in &grades {
+            let received = match g.received {
+                Some(received) => format!("received {received:>5.2}"),
+                None => "no evaluations".into(),
+            };
+
+            println!(
+                "\t{:<30} {}  {:>6.2} × {:.3} = {:.2}",
+                g.student.sortable_name, received, group_grade, g.factor, g.score
+            );
+        }
+
+        if !Confirm::with_theme(&theme)
+            .with_prompt("Post these grades?")
+            .interact()?
+        {
+            continue;
+        }
+
+        for g in &grades {
+            let previous = client
+                .get_submission_grade(course_id, assignment_id, g.student.id)
+                .await?
+                .score;
+
+            client
+                .put_grade(course_id, assignment_id, g.student.id, g.score)
+                .await?;
+
+            let mut record =
+                history::Record::new(course_id, assignment_id, assignment_name, g.student.id);
+            record.score = Some(g.score);
+            record.previous_score = previous;
+            history::append(&record).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: usize) -> canvas::User {
+        canvas::User {
+            id,
+            name: id.to_string(),
+            sortable_name: id.to_string(),
+            login_id: None,
+            email: None,
+            sis_user_id: None,
+        }
+    }
+
+    #[test]
+    fn adjust_scales_by_the_group_mean() {
+        let (a, b, c) = (user(1), user(2), user(3));
+        let members = BTreeMap::from([
+            (1, (&a, vec![4.0, 4.0])),
+            (2, (&b, vec![2.0])),
+            (3, (&c, vec![])),
+        ]);
+
+        let scores: Vec<_> = adjust(90.0, Some(100.0), &members)
+            .iter()
+            .map(|g| (g.student.id, g.received, g.score))
+            .collect();
+        assert_eq!(
+            scores,
+            [(1, Some(4.0), 100.0), (2, Some(2.0), 60.0), (3, None, 90.0)]
+        );
+    }
+
+    #[test]
+    fn adjust_keeps_the_grade_without_evaluations() {
+        let a = user(1);
+        let members = BTreeMap::from([(1, (&a, vec![]))]);
+
+        let grades = adjust(80.0, None, &members);
+        assert_eq!((grades[0].factor, grades[0].score), (1.0, 80.0));
+    }
+}
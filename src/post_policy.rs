@@ -0,0 +1,166 @@
+//! Canvas's post policy decides when students see their grades. Held grades
+//! stay hidden until released, so a portion can be released all at once
+//! instead of students seeing grades trickle in. The policy is the whole
+//! assignment's, so while it is held other graders' grades are hidden too;
+//! it is put back once grading is done. Post policies are only in canvas's
+//! GraphQL API.
+
+use crate::canvas;
+use serde::Deserialize;
+use serde_json::json;
+
+const POLICY: &str = r#"
+query Policy($assignment: ID!) {
+  assignment(id: $assignment) {
+    postPolicy {
+      postManually
+    }
+  }
+}
+"#;
+
+const SET: &str = r#"
+mutation Set($assignment: ID!, $manually: Boolean!) {
+  setAssignmentPostPolicy(input: { assignmentId: $assignment, postManually: $manually }) {
+    errors {
+      message
+    }
+  }
+}
+"#;
+
+const RELEASE: &str = r#"
+mutation Release($assignment: ID!, $students: [ID!]) {
+  postAssignmentGrades(
+    input: { assignmentId: $assignment, gradedOnly: true, onlyStudentIds: $students }
+  ) {
+    errors {
+      message
+    }
+  }
+}
+"#;
+
+#[derive(Debug, Deserialize)]
+struct Payload {
+    #[serde(default)]
+    errors: Option<Vec<Error>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Error {
+    message: String,
+}
+
+impl Payload {
+    fn check(self) -> Result<(), Box<dyn std::error::Error>> {
+        match self.errors.unwrap_or_default().into_iter().next() {
+            Some(error) => Err(error.message.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Whether the assignment's grades are hidden until released.
+async fn manual(
+    client: &canvas::Client,
+    assignment_id: usize,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    #[derive(Deserialize)]
+    struct Data {
+        assignment: Option<Assignment>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Assignment {
+        post_policy: Option<Policy>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Policy {
+        post_manually: bool,
+    }
+
+    Ok(client
+        .graphql::<Data>(POLICY, json!({ "assignment": assignment_id.to_string() }))
+        .await?
+        .assignment
+        .and_then(|a| a.post_policy)
+        .is_some_and(|p| p.post_manually))
+}
+
+async fn set(
+    client: &canvas::Client,
+    assignment_id: usize,
+    manually: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Data {
+        set_assignment_post_policy: Payload,
+    }
+
+    client
+        .graphql::<Data>(
+            SET,
+            json!({ "assignment": assignment_id.to_string(), "manually": manually }),
+        )
+        .await?
+        .set_assignment_post_policy
+        .check()
+}
+
+/// Makes the assignment's grades hidden until released, returning whether
+/// they already were, for [`restore`].
+pub async fn hold(
+    client: &canvas::Client,
+    assignment_id: usize,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let held = manual(client, assignment_id).await?;
+
+    if !held {
+        set(client, assignment_id, true).await?;
+    }
+
+    Ok(held)
+}
+
+/// Puts back the policy [`hold`] found. Grades still held stay hidden until
+/// released.
+pub async fn restore(
+    client: &canvas::Client,
+    assignment_id: usize,
+    held: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match held {
+        true => Ok(()),
+        false => set(client, assignment_id, false).await,
+    }
+}
+
+/// Shows the students their grades, everyone graded when no students are
+/// given.
+pub async fn release(
+    client: &canvas::Client,
+    assignment_id: usize,
+    students: Option<&[usize]>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Data {
+        post_assignment_grades: Payload,
+    }
+
+    let students: Option<Vec<_>> = students.map(|ids| ids.iter().map(usize::to_string).collect());
+
+    client
+        .graphql::<Data>(
+            RELEASE,
+            json!({ "assignment": assignment_id.to_string(), "students": students }),
+        )
+        .await?
+        .post_assignment_grades
+        .check()
+}
@@ -0,0 +1,109 @@
+//! Named canvas profiles for graders at more than one institution, read from
+//! `GRADER_PROFILES` (default `~/.config/grader/profiles.toml`):
+//!
+//! ```toml
+//! [profiles.osu]
+//! base_url = "https://canvas.oregonstate.edu/"
+//! access_token = "..."
+//!
+//! [profiles.cmu]
+//! base_url = "https://canvas.cmu.edu/"
+//! access_token = "..."
+//! ```
+//!
+//! A profile can give a developer key (`client_id` and `client_secret`)
+//! instead of an access token to log in through the browser, see
+//! [`crate::oauth`].
+//!
+//! A token saved in the system keyring with `grader save-token` is used
+//! over the configured one, see [`crate::secrets`].
+//!
+//! Without the file `CANVAS_BASE_URL` and `CANVAS_ACCESS_TOKEN` (or
+//! `CANVAS_CLIENT_ID` and `CANVAS_CLIENT_SECRET`) are used.
+
+use crate::secrets;
+use dialoguer::{theme::ColorfulTheme, Select};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Profile {
+    pub base_url: String,
+    pub access_token: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Config {
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+fn path() -> PathBuf {
+    env::var("GRADER_PROFILES")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            PathBuf::from(env::var("HOME").unwrap_or_default()).join(".config/grader/profiles.toml")
+        })
+}
+
+async fn load() -> Result<Config, Box<dyn std::error::Error>> {
+    match tokio::fs::read_to_string(path()).await {
+        Ok(contents) => Ok(toml::from_str(&contents)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn from_env() -> Result<Profile, Box<dyn std::error::Error>> {
+    Ok(Profile {
+        base_url: env::var("CANVAS_BASE_URL").map_err(|_| "CANVAS_BASE_URL is not set")?,
+        access_token: env::var("CANVAS_ACCESS_TOKEN").ok(),
+        client_id: env::var("CANVAS_CLIENT_ID").ok(),
+        client_secret: env::var("CANVAS_CLIENT_SECRET").ok(),
+    })
+}
+
+/// The profile named on the command line, the only one configured, or the
+/// one picked from the list.
+async fn pick(name: Option<&str>) -> Result<Profile, Box<dyn std::error::Error>> {
+    let mut profiles = load().await?.profiles;
+
+    if let Some(name) = name {
+        return profiles
+            .remove(name)
+            .ok_or_else(|| format!("no profile named {name} in {}", path().display()).into());
+    }
+
+    let names: Vec<_> = profiles.keys().cloned().collect();
+
+    let name = match names.as_slice() {
+        [] => return from_env(),
+        [name] => name,
+        _ => {
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Profile")
+                .items(&names)
+                .default(0)
+                .interact()?;
+
+            &names[selection]
+        }
+    };
+
+    Ok(profiles.remove(name).unwrap())
+}
+
+/// Like `pick`, using the token saved in the keyring over the configured one.
+pub async fn select(name: Option<&str>) -> Result<Profile, Box<dyn std::error::Error>> {
+    let mut profile = pick(name).await?;
+
+    if let Some(token) = secrets::token(&profile.base_url) {
+        profile.access_token = Some(token);
+    }
+
+    Ok(profile)
+}
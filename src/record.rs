@@ -0,0 +1,70 @@
+//! Records the interactive part of grading each student (the shell, editors
+//! and gdb) with asciinema, to `<student>.cast` next to their feedback. The
+//! casts can be replayed with `asciinema play` when a grade is disputed or to
+//! show new graders the workflow.
+
+use crate::sandbox::quote;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The cast of the student being graded, and its title.
+#[derive(Debug)]
+pub struct Recording {
+    cast: PathBuf,
+    title: String,
+}
+
+impl Recording {
+    /// Stops recording, returning the cast if anything was recorded.
+    pub fn stop(self) -> Option<PathBuf> {
+        Some(self.cast).filter(|c| c.exists())
+    }
+}
+
+/// Starts recording to the cast next to the student's directory.
+pub fn start(dir: &Path, title: &str) -> Recording {
+    let mut cast = dir.to_path_buf().into_os_string();
+    cast.push(".cast");
+    let cast = std::env::current_dir()
+        .map(|cwd| cwd.join(&cast))
+        .unwrap_or(cast.into());
+
+    Recording {
+        cast,
+        title: title.to_string(),
+    }
+}
+
+/// The command to run an interactive program, appended to the cast when
+/// recording.
+pub fn command<I, S>(program: &str, args: I, recording: Option<&Recording>) -> Command
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let args: Vec<String> = args.into_iter().map(|a| a.as_ref().to_string()).collect();
+
+    let Some(Recording { cast, title }) = recording else {
+        let mut command = Command::new(program);
+        command.args(args);
+        return command;
+    };
+
+    let line = std::iter::once(program.to_string())
+        .chain(args)
+        .map(|a| quote(&a))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut command = Command::new("asciinema");
+    command.args(["rec", "--quiet", "--append", "--command"]);
+    command.arg(line);
+
+    // the title can only be given when the cast is created
+    if !cast.exists() {
+        command.arg("--title").arg(title);
+    }
+
+    command.arg(cast);
+    command
+}
@@ -0,0 +1,158 @@
+//! Repository url submissions, cloned into the workspace at the last commit
+//! before the deadline.
+//!
+//! Commits are picked by their commit date, which the student sets, so a
+//! commit backdated with e.g. `git commit --date` still counts as on time.
+//! git keeps no record of when a commit was pushed; check the host's push
+//! log when the timing matters.
+
+use crate::Errors;
+use chrono::{DateTime, Utc};
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// Strips the web ui parts from a repository url, e.g.
+/// `https://github.com/user/repo/tree/main` or
+/// `https://gitlab.com/user/repo/-/tree/main`. Only https and ssh urls are
+/// cloned, anything else, e.g. `file://` or an option for git, is `None`.
+pub fn normalize_url(url: &str) -> Option<String> {
+    let url = url.trim().trim_end_matches('/');
+
+    if !["https://", "ssh://", "git@"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+    {
+        return None;
+    }
+
+    let url = ["/-/", "/tree/", "/blob/", "/commit/"]
+        .iter()
+        .filter_map(|marker| url.find(marker))
+        .min()
+        .map_or(url, |i| &url[..i]);
+
+    Some(url.to_string())
+}
+
+/// Commits up to the deadline count, or up to the submission time for late
+/// submissions.
+pub fn cutoff(
+    submitted_at: Option<DateTime<Utc>>,
+    due_at: Option<DateTime<Utc>>,
+) -> Option<DateTime<Utc>> {
+    match (submitted_at, due_at) {
+        (Some(submitted_at), Some(due_at)) => Some(submitted_at.max(due_at)),
+        (submitted_at, due_at) => due_at.or(submitted_at),
+    }
+}
+
+/// Git that fails instead of asking for credentials, e.g. for a private or
+/// mistyped repository.
+fn command() -> Command {
+    let mut git = Command::new("git");
+    git.env("GIT_TERMINAL_PROMPT", "0").stdin(Stdio::null());
+    git
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = command().arg("-C").arg(dir).args(args).output().await?;
+
+    if !output.status.success() {
+        return Err(Errors::CloneFailed.into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Clones the repository, or fetches it when it was cloned by an earlier
+/// run, and checks out the last commit before `before`, returning the commit
+/// hash.
+pub async fn clone_at(
+    url: &str,
+    path: &Path,
+    before: Option<DateTime<Utc>>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if path.join(".git").is_dir() {
+        git(path, &["fetch", "--quiet", "origin"]).await?;
+    } else {
+        let url = normalize_url(url).ok_or(Errors::CloneFailed)?;
+
+        // local paths are never cloned, whatever the url redirects to
+        let status = command()
+            .args(["-c", "protocol.file.allow=never", "clone", "--quiet", "--"])
+            .arg(url)
+            .arg(path)
+            .status()
+            .await?;
+
+        if !status.success() {
+            return Err(Errors::CloneFailed.into());
+        }
+    }
+
+    let commit = match before {
+        Some(before) => {
+            git(
+                path,
+                &[
+                    "rev-list",
+                    "-n",
+                    "1",
+                    &format!("--before={}", before.to_rfc3339()),
+                    "origin/HEAD",
+                ],
+            )
+            .await?
+        }
+        None => git(path, &["rev-parse", "origin/HEAD"]).await?,
+    };
+
+    if commit.is_empty() {
+        return Err(Errors::NoCommitBeforeSubmission.into());
+    }
+
+    git(path, &["checkout", "--quiet", "--detach", &commit]).await?;
+
+    Ok(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_web_ui_paths() {
+        assert_eq!(
+            normalize_url("https://github.com/user/repo/tree/main/src/").as_deref(),
+            Some("https://github.com/user/repo")
+        );
+        assert_eq!(
+            normalize_url(" https://gitlab.com/user/repo/-/tree/main ").as_deref(),
+            Some("https://gitlab.com/user/repo")
+        );
+        assert_eq!(
+            normalize_url("git@github.com:user/repo.git").as_deref(),
+            Some("git@github.com:user/repo.git")
+        );
+    }
+
+    #[test]
+    fn rejects_other_schemes() {
+        assert_eq!(normalize_url("--upload-pack=touch /tmp/x"), None);
+        assert_eq!(normalize_url("file:///home/grader/.ssh"), None);
+        assert_eq!(normalize_url("/home/grader/repo"), None);
+        assert_eq!(normalize_url("http://github.com/user/repo"), None);
+    }
+
+    #[test]
+    fn cutoff_is_the_later_of_submission_and_deadline() {
+        let due: DateTime<Utc> = "2024-10-01T23:59:00Z".parse().unwrap();
+        let late: DateTime<Utc> = "2024-10-02T08:00:00Z".parse().unwrap();
+
+        assert_eq!(cutoff(Some(late), Some(due)), Some(late));
+        assert_eq!(cutoff(Some(due), Some(late)), Some(late));
+        assert_eq!(cutoff(None, Some(due)), Some(due));
+        assert_eq!(cutoff(Some(late), None), Some(late));
+    }
+}
@@ -0,0 +1,197 @@
+//! The portion being graded and how far grading got, kept in
+//! `.grader/session.json` so a crash or quitting halfway through can be
+//! picked up where it stopped. The score, comment and note decided for each
+//! student stay in it as a record, whether or not they were posted. Starting
+//! a new session moves the previous one to the end of `sessions.jsonl`
+//! beside it, so no record is lost.
+
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    Graded,
+    /// gone through without posting anything
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Student {
+    pub assignment_id: usize,
+    pub user_id: usize,
+    pub status: Status,
+    #[serde(default)]
+    pub score: Option<f64>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// the grader's own note, never posted
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Progress {
+    pub course_id: usize,
+    pub course_name: String,
+    pub assignment_ids: Vec<usize>,
+    /// e.g. "2 of 4"
+    pub portion: String,
+    pub students: Vec<Student>,
+    /// what the students were shuffled with, when grading in random order
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl Progress {
+    pub fn new(
+        course_id: usize,
+        course_name: &str,
+        assignment_ids: Vec<usize>,
+        portion: String,
+    ) -> Self {
+        Self {
+            course_id,
+            course_name: course_name.to_string(),
+            assignment_ids,
+            portion,
+            students: vec![],
+            seed: None,
+        }
+    }
+
+    /// Whether the student is in the portion.
+    pub fn contains(&self, assignment_id: usize, user_id: usize) -> bool {
+        self.status(assignment_id, user_id).is_some()
+    }
+
+    pub fn status(&self, assignment_id: usize, user_id: usize) -> Option<Status> {
+        self.students
+            .iter()
+            .find(|s| s.assignment_id == assignment_id && s.user_id == user_id)
+            .map(|s| s.status)
+    }
+
+    /// Adds the student as pending, keeping their status when resuming.
+    pub fn add(&mut self, assignment_id: usize, user_id: usize) {
+        if !self.contains(assignment_id, user_id) {
+            self.students.push(Student {
+                assignment_id,
+                user_id,
+                status: Status::Pending,
+                score: None,
+                comment: None,
+                note: None,
+            });
+        }
+    }
+
+    /// Records what was decided for the student.
+    pub fn set(
+        &mut self,
+        assignment_id: usize,
+        user_id: usize,
+        status: Status,
+        score: Option<f64>,
+        comment: Option<String>,
+        note: Option<String>,
+    ) {
+        self.add(assignment_id, user_id);
+
+        if let Some(student) = self
+            .students
+            .iter_mut()
+            .find(|s| s.assignment_id == assignment_id && s.user_id == user_id)
+        {
+            student.status = status;
+            student.score = score;
+            student.comment = comment;
+            student.note = note;
+        }
+    }
+
+    fn count(&self, status: Status) -> usize {
+        self.students.iter().filter(|s| s.status == status).count()
+    }
+
+    /// Whether every student has been gone through.
+    pub fn done(&self) -> bool {
+        self.count(Status::Pending) == 0
+    }
+
+    pub async fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        fs::write(path, serde_json::to_string_pretty(self)?).await?;
+
+        Ok(())
+    }
+}
+
+fn path() -> PathBuf {
+    env::var("GRADER_SESSION")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(".grader/session.json"))
+}
+
+/// Moves the previous session, if any, to the end of the log of past
+/// sessions before a new one replaces it.
+pub async fn archive() -> Result<(), Box<dyn std::error::Error>> {
+    let path = path();
+    let Some(previous) = load().await else {
+        return Ok(());
+    };
+
+    let log = path.with_file_name("sessions.jsonl");
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log)
+        .await?;
+    file.write_all(format!("{}\n", serde_json::to_string(&previous)?).as_bytes())
+        .await?;
+
+    fs::remove_file(path).await?;
+
+    Ok(())
+}
+
+pub fn print_path() {
+    println!("Decisions recorded in {}", path().display());
+}
+
+/// The last session, finished or not.
+pub async fn load() -> Option<Progress> {
+    let contents = fs::read_to_string(path()).await.ok()?;
+
+    serde_json::from_str(&contents).ok()
+}
+
+/// Offers to resume the session left unfinished, if there is one.
+pub async fn offer() -> Result<Option<Progress>, Box<dyn std::error::Error>> {
+    let Some(progress) = load().await.filter(|p| !p.done()) else {
+        return Ok(None);
+    };
+
+    let resume = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "Resume previous session ({}, portion {}, {} of {} graded)?",
+            progress.course_name,
+            progress.portion,
+            progress.count(Status::Graded),
+            progress.students.len()
+        ))
+        .default(true)
+        .interact()?;
+
+    Ok(resume.then_some(progress))
+}
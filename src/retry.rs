@@ -0,0 +1,173 @@
+//! Retries for flaky requests to canvas and file downloads, with exponential
+//! backoff and jitter.
+//!
+//! `GRADER_RETRIES` sets the number of attempts (default 5).
+
+use crate::throttle;
+use colored::Colorize;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use std::env;
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const ATTEMPTS: usize = 5;
+
+const BASE_DELAY: Duration = Duration::from_millis(500);
+
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+fn attempts() -> usize {
+    env::var("GRADER_RETRIES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(ATTEMPTS)
+        .max(1)
+}
+
+/// Doubles with each attempt, with a random half taken off so many clients
+/// do not retry in lockstep.
+fn backoff(attempt: usize) -> Duration {
+    let delay = BASE_DELAY
+        .saturating_mul(1 << attempt.min(16))
+        .min(MAX_DELAY);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let jitter = nanos as f64 / 1e9;
+
+    delay.mul_f64(1.0 - jitter / 2.0)
+}
+
+/// Throttled requests and ones that never connected weren't carried out, so
+/// anything can be sent again. Server errors and timeouts may come after a
+/// write went through, so only reads are retried on those, lest a comment be
+/// posted twice.
+fn retryable(result: &Result<Response, reqwest::Error>, idempotent: bool) -> bool {
+    match result {
+        Ok(response) => {
+            response.status() == StatusCode::TOO_MANY_REQUESTS
+                || (response.status() == StatusCode::FORBIDDEN
+                    && throttle::exceeded(response.headers()))
+                || (idempotent && response.status().is_server_error())
+        }
+        Err(e) => e.is_connect() || (idempotent && e.is_timeout()),
+    }
+}
+
+/// Whether the error is worth retrying, a timeout, dropped connection,
+/// server error or throttling rather than e.g. a 401 or 404.
+fn transient(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(error);
+
+    while let Some(error) = source {
+        if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+            return e.is_timeout()
+                || e.is_connect()
+                || e.status().is_some_and(|s| {
+                    s.is_server_error()
+                        || s == StatusCode::TOO_MANY_REQUESTS
+                        || s == StatusCode::FORBIDDEN
+                });
+        }
+        if error.is::<serde_json::Error>() {
+            return false;
+        }
+        source = error.source();
+    }
+
+    // canvasapi's own errors only say what went wrong in the message
+    let message = error.to_string();
+    ![
+        "401",
+        "403",
+        "404",
+        "Unauthorized",
+        "Forbidden",
+        "Not Found",
+    ]
+    .iter()
+    .any(|status| message.contains(status))
+}
+
+/// Waits before the next attempt, or fails once every attempt is used.
+async fn wait(attempt: usize, reason: String) -> Result<(), Box<dyn std::error::Error>> {
+    let attempts = attempts();
+
+    if attempt >= attempts {
+        return Err(format!("request failed after {attempts} attempts: {reason}").into());
+    }
+
+    let delay = backoff(attempt - 1);
+
+    println!(
+        "{} {}, retrying in {:.1}s ({}/{})",
+        "warning:".yellow(),
+        reason,
+        delay.as_secs_f64(),
+        attempt,
+        attempts
+    );
+
+    tokio::time::sleep(delay).await;
+
+    Ok(())
+}
+
+/// Sends the request, retrying throttling and failed connections, and for
+/// reads server errors and timeouts too. Other error statuses fail straight
+/// away.
+pub async fn send(request: RequestBuilder) -> Result<Response, Box<dyn std::error::Error>> {
+    let idempotent = request
+        .try_clone()
+        .and_then(|r| r.build().ok())
+        .is_some_and(|r| matches!(*r.method(), Method::GET | Method::HEAD));
+
+    for attempt in 1.. {
+        throttle::wait().await;
+
+        let result = request
+            .try_clone()
+            .ok_or("request body cannot be retried")?
+            .send()
+            .await;
+
+        if let Ok(response) = &result {
+            throttle::update(response.headers());
+        }
+
+        if !retryable(&result, idempotent) {
+            return Ok(result?.error_for_status()?);
+        }
+
+        let reason = match &result {
+            Ok(response) => response.status().to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        wait(attempt, reason).await?;
+    }
+
+    unreachable!()
+}
+
+/// Retries requests made through `canvasapi`, which hides the response, on
+/// transient errors.
+pub async fn retry<T, F, Fut>(mut f: F) -> Result<T, Box<dyn std::error::Error>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Box<dyn std::error::Error>>>,
+{
+    for attempt in 1.. {
+        throttle::wait().await;
+
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(e) if transient(e.as_ref()) => wait(attempt, e.to_string()).await?,
+            Err(e) => return Err(e),
+        }
+    }
+
+    unreachable!()
+}
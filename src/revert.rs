@@ -0,0 +1,100 @@
+//! Undoes a grade posted by mistake, to the wrong student or with a mistyped
+//! score, using the score it replaced and the comment it posted as recorded
+//! in the history.
+
+use crate::{canvas, history};
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+
+/// Restores the previous score, deletes the posted comment and records the
+/// revert in the history.
+pub async fn undo(
+    client: &canvas::Client,
+    record: &history::Record,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (course_id, assignment_id, user_id) =
+        (record.course_id, record.assignment_id, record.user_id);
+
+    // reverting would overwrite the final grade the moderator picks
+    if record.provisional && record.score.is_some() {
+        return Err("provisional grades can only be changed in SpeedGrader".into());
+    }
+
+    // comments posted on their own leave the score alone
+    match (record.score, record.previous_score) {
+        (_, Some(score)) => {
+            client
+                .put_grade(course_id, assignment_id, user_id, score)
+                .await?
+        }
+        (Some(_), None) => {
+            client
+                .clear_grade(course_id, assignment_id, user_id)
+                .await?
+        }
+        (None, None) => {}
+    }
+
+    if let Some(comment_id) = record.comment_id {
+        client
+            .delete_comment(course_id, assignment_id, user_id, comment_id)
+            .await?;
+    }
+
+    let mut revert =
+        history::Record::new(course_id, assignment_id, &record.assignment_name, user_id);
+    revert.score = record.previous_score;
+    revert.previous_score = record.score;
+
+    history::append(&revert).await
+}
+
+/// Reverts the last grade this grader posted to the assignment, for the
+/// student if given.
+pub async fn run(
+    client: &canvas::Client,
+    course_id: usize,
+    assignment_id: usize,
+    students: &[canvas::User],
+    student: Option<&canvas::User>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let records = history::load(history::Scope::Mine).await?;
+
+    let Some(record) = records.iter().rev().find(|r| {
+        r.course_id == course_id
+            && r.assignment_id == assignment_id
+            && student.map(|s| s.id == r.user_id).unwrap_or(true)
+    }) else {
+        println!("Nothing posted to revert");
+        return Ok(());
+    };
+
+    let name = students
+        .iter()
+        .find(|s| s.id == record.user_id)
+        .map(|s| s.sortable_name.clone())
+        .unwrap_or_else(|| record.user_id.to_string());
+
+    println!("Last posted to {}:", name.bright_blue());
+    record.print();
+
+    let previous = record
+        .previous_score
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| "no score".into());
+
+    let prompt = match record.comment_id {
+        Some(_) => format!("Restore {previous} and delete the comment?"),
+        None => format!("Restore {previous}?"),
+    };
+
+    if Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .interact()?
+    {
+        undo(client, record).await?;
+        println!("Reverted");
+    }
+
+    Ok(())
+}
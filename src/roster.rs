@@ -0,0 +1,125 @@
+//! Roster csv (from the registrar or a canvas gradebook export) used to fill
+//! in what canvas profiles are missing: preferred names, name.# usernames and
+//! sections.
+//!
+//! Columns are matched by header, so any export with some of `ID`, `Student`,
+//! `Preferred Name`, `SIS Login ID`/`Username`, `Email` and `Section` works.
+
+use std::path::Path;
+
+const ID: &[&str] = &["id", "canvas id", "user id", "canvas user id"];
+const NAME: &[&str] = &["student", "name", "student name", "sortable name"];
+const PREFERRED: &[&str] = &["preferred name", "preferred first name", "preferred"];
+const LOGIN: &[&str] = &["sis login id", "login id", "login", "username", "user name"];
+const EMAIL: &[&str] = &["email", "email address"];
+const SECTION: &[&str] = &["section", "sections", "section name"];
+
+#[derive(Debug, Default, Clone)]
+pub struct Entry {
+    pub user_id: Option<usize>,
+    pub name: Option<String>,
+    pub preferred_name: Option<String>,
+    pub login_id: Option<String>,
+    pub email: Option<String>,
+    pub section: Option<String>,
+}
+
+impl Entry {
+    /// Identifiers besides the last name a student may have used in their
+    /// files.
+    pub fn aliases(&self) -> Vec<String> {
+        [&self.preferred_name, &self.login_id]
+            .into_iter()
+            .flatten()
+            .map(|a| a.to_lowercase())
+            .collect()
+    }
+
+    pub fn describe(&self) -> String {
+        [&self.preferred_name, &self.login_id, &self.section]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Roster {
+    entries: Vec<Entry>,
+}
+
+/// Lower case letters and digits only, so "Doe, Jane" matches the "doejane"
+/// of canvas bulk downloads.
+pub fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl Roster {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut reader = csv::Reader::from_path(path)?;
+
+        let headers: Vec<_> = reader
+            .headers()?
+            .iter()
+            .map(|h| h.trim().to_lowercase())
+            .collect();
+        let column = |aliases: &[&str]| headers.iter().position(|h| aliases.contains(&h.as_str()));
+
+        let (id, name, preferred, login, email, section) = (
+            column(ID),
+            column(NAME),
+            column(PREFERRED),
+            column(LOGIN),
+            column(EMAIL),
+            column(SECTION),
+        );
+
+        let mut entries = vec![];
+
+        for record in reader.records() {
+            let record = record?;
+            let field = |i: Option<usize>| {
+                i.and_then(|i| record.get(i))
+                    .map(str::trim)
+                    .filter(|f| !f.is_empty())
+                    .map(String::from)
+            };
+
+            let entry = Entry {
+                user_id: field(id).and_then(|id| id.parse().ok()),
+                name: field(name),
+                preferred_name: field(preferred),
+                login_id: field(login),
+                email: field(email),
+                section: field(section),
+            };
+
+            // gradebook exports have a "Points Possible" row
+            if entry.user_id.is_none() && entry.login_id.is_none() {
+                continue;
+            }
+
+            entries.push(entry);
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn find(&self, user_id: usize, name: &str) -> Option<&Entry> {
+        let name = normalize(name);
+
+        self.entries
+            .iter()
+            .find(|e| e.user_id == Some(user_id))
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .find(|e| e.name.as_deref().map(normalize).as_deref() == Some(name.as_str()))
+            })
+    }
+}
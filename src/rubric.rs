@@ -0,0 +1,158 @@
+//! Scoring with the assignment's canvas rubric, one rating per criterion.
+
+use crate::canvas::{Criterion, Rating};
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Input, Select};
+
+#[derive(Debug)]
+pub struct Assessed<'a> {
+    pub criterion: &'a Criterion,
+    pub rating: &'a Rating,
+    pub comment: String,
+}
+
+/// The rating picked for each criterion.
+#[derive(Debug)]
+pub struct RubricAssessment<'a> {
+    pub ratings: Vec<Assessed<'a>>,
+}
+
+impl RubricAssessment<'_> {
+    pub fn score(&self) -> f64 {
+        self.ratings.iter().map(|a| a.rating.points).sum()
+    }
+
+    pub fn points_possible(&self) -> f64 {
+        self.ratings.iter().map(|a| a.criterion.points).sum()
+    }
+
+    pub fn itemize(&self) -> String {
+        self.ratings
+            .iter()
+            .map(|a| {
+                let mut line = format!(
+                    "{}: {} ({}/{})",
+                    a.criterion.description,
+                    a.rating.description,
+                    a.rating.points,
+                    a.criterion.points
+                );
+
+                if !a.comment.is_empty() {
+                    line.push_str(&format!(", {}", a.comment));
+                }
+
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn print(&self) {
+        println!("Rubric:");
+
+        for a in &self.ratings {
+            println!(
+                "\t{:<30} {} ({}/{})",
+                a.criterion.description,
+                a.rating.description.bright_blue(),
+                a.rating.points,
+                a.criterion.points
+            );
+
+            if !a.comment.is_empty() {
+                println!("\t\t{}", a.comment.dimmed());
+            }
+        }
+
+        println!("\tTotal {}/{}", self.score(), self.points_possible());
+    }
+
+    /// The form fields canvas expects for a rubric assessment.
+    pub fn form(&self) -> Vec<(String, String)> {
+        self.ratings
+            .iter()
+            .flat_map(|a| {
+                let field = |name| format!("rubric_assessment[{}][{}]", a.criterion.id, name);
+
+                [
+                    (field("points"), a.rating.points.to_string()),
+                    (field("rating_id"), a.rating.id.clone()),
+                    (field("comments"), a.comment.clone()),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Asks for the criterion's rating, and a comment explaining any points
+/// taken off.
+fn rate(criterion: &Criterion) -> Result<Assessed, Box<dyn std::error::Error>> {
+    let items: Vec<_> = criterion
+        .ratings
+        .iter()
+        .map(|r| format!("{:>5} {}", r.points, r.description))
+        .collect();
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "{} ({} pts)",
+            criterion.description, criterion.points
+        ))
+        .items(&items)
+        .default(0)
+        .interact()?;
+
+    let rating = &criterion.ratings[selection];
+
+    let comment = match rating.points < criterion.points {
+        true => Input::<String>::with_theme(&ColorfulTheme::default())
+            .with_prompt("Comment")
+            .allow_empty(true)
+            .interact_text()?,
+        false => String::new(),
+    };
+
+    Ok(Assessed {
+        criterion,
+        rating,
+        comment,
+    })
+}
+
+/// Asks for a rating per criterion, then shows the assessment so any rating
+/// can be changed before it is final.
+pub fn assess(rubric: &[Criterion]) -> Result<RubricAssessment, Box<dyn std::error::Error>> {
+    let mut assessment = RubricAssessment {
+        ratings: rubric
+            .iter()
+            .filter(|c| !c.ratings.is_empty())
+            .map(rate)
+            .collect::<Result<_, _>>()?,
+    };
+
+    loop {
+        assessment.print();
+
+        let items: Vec<_> = std::iter::once("Done".to_string())
+            .chain(
+                assessment
+                    .ratings
+                    .iter()
+                    .map(|a| format!("Change {}", a.criterion.description)),
+            )
+            .collect();
+
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .items(&items)
+            .default(0)
+            .interact()?;
+
+        if selection == 0 {
+            return Ok(assessment);
+        }
+
+        let criterion = assessment.ratings[selection - 1].criterion;
+        assessment.ratings[selection - 1] = rate(criterion)?;
+    }
+}
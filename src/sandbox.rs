@@ -0,0 +1,184 @@
+//! Where checks run the student's code. By default they run directly in the
+//! grader's shell; `--sandbox bubblewrap` or `--sandbox docker` runs them
+//! with only the student's directory writable, the directories a check
+//! reads from the assignment (e.g. `tests/`) read-only, no network and none
+//! of the grader's environment, e.g. the canvas token. gdb sessions run in it
+//! too.
+//!
+//! Docker runs `GRADER_SANDBOX_IMAGE` (default `gcc`), which needs whatever
+//! the checks use, e.g. make, gdb and GNU time.
+
+use crate::record;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::process::Command;
+
+const IMAGE: &str = "gcc";
+
+/// `PATH` inside the sandbox.
+const PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// System directories bubblewrap mounts read-only, when they exist.
+const SYSTEM: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/opt",
+];
+
+/// Quotes an argument for `sh -c`.
+pub fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum Backend {
+    #[default]
+    Host,
+    Bubblewrap,
+    Docker,
+}
+
+/// Numbers docker containers, so a timed out one can be stopped by name.
+static CONTAINERS: AtomicUsize = AtomicUsize::new(0);
+
+/// A command set up to run in the sandbox.
+pub struct Sandboxed {
+    pub command: Command,
+    container: Option<String>,
+}
+
+impl Sandboxed {
+    /// Stops the command's container, which outlives the docker client
+    /// being killed.
+    pub fn stop(&self) {
+        if let Some(name) = &self.container {
+            let _ = std::process::Command::new("docker")
+                .args(["kill", name])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+    }
+}
+
+pub fn absolute(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The program and arguments running `sh -c <command>` in `dir` with
+/// `backend`, `mounts` readable, and the container's name.
+fn invocation(
+    command: &str,
+    dir: &Path,
+    mounts: &[PathBuf],
+    backend: Backend,
+    interactive: bool,
+) -> (&'static str, Vec<String>, Option<String>) {
+    let dir = absolute(dir).display().to_string();
+    let mounts: Vec<_> = mounts
+        .iter()
+        .map(|m| absolute(m))
+        .filter(|m| m.is_dir())
+        .map(|m| m.display().to_string())
+        .collect();
+    let sh = ["sh".to_string(), "-c".into(), command.into()];
+
+    match backend {
+        Backend::Host => ("sh", sh[1..].to_vec(), None),
+        Backend::Bubblewrap => {
+            let mut args: Vec<String> = ["--unshare-all", "--die-with-parent", "--clearenv"]
+                .into_iter()
+                .chain(["--setenv", "PATH", PATH, "--setenv", "HOME", "/tmp"])
+                .map(String::from)
+                .collect();
+
+            for system in SYSTEM {
+                args.extend(["--ro-bind-try", system, system].map(String::from));
+            }
+
+            args.extend(["--proc", "/proc", "--dev", "/dev", "--tmpfs", "/tmp"].map(String::from));
+            for mount in mounts {
+                args.extend(["--ro-bind".into(), mount.clone(), mount]);
+            }
+            args.extend(["--bind".into(), dir.clone(), dir.clone()]);
+            args.extend(["--chdir".into(), dir]);
+            args.extend(sh);
+
+            ("bwrap", args, None)
+        }
+        Backend::Docker => {
+            let name = format!(
+                "grader-{}-{}",
+                std::process::id(),
+                CONTAINERS.fetch_add(1, Ordering::Relaxed)
+            );
+            let image = std::env::var("GRADER_SANDBOX_IMAGE").unwrap_or_else(|_| IMAGE.into());
+
+            let mut args: Vec<String> =
+                ["run", "--rm", "--network", "none", "--name", name.as_str()]
+                    .map(String::from)
+                    .into();
+
+            if interactive {
+                args.extend(["--interactive", "--tty"].map(String::from));
+            }
+
+            // so files the build writes belong to the grader, not root
+            if let Ok(metadata) = std::fs::metadata(&dir) {
+                args.extend([
+                    "--user".into(),
+                    format!("{}:{}", metadata.uid(), metadata.gid()),
+                ]);
+            }
+
+            for mount in mounts {
+                args.extend(["--volume".into(), format!("{mount}:{mount}:ro")]);
+            }
+            args.extend(["--volume".into(), format!("{dir}:{dir}")]);
+            args.extend(["--workdir".into(), dir]);
+            args.extend(["--env", "HOME=/tmp"].map(String::from));
+            args.push(image);
+            args.extend(sh);
+
+            ("docker", args, Some(name))
+        }
+    }
+}
+
+/// Runs `sh -c <command>` in `dir` with `backend`, `mounts` being the
+/// directories outside it the command reads.
+pub fn command(command: &str, dir: &Path, mounts: &[PathBuf], backend: Backend) -> Sandboxed {
+    let (program, args, container) = invocation(command, dir, mounts, backend, false);
+
+    let mut command = Command::new(program);
+    command.args(args).current_dir(dir);
+
+    Sandboxed { command, container }
+}
+
+/// Like [`command`], for a program that takes over the terminal, e.g. gdb,
+/// recorded with the rest of the session.
+pub fn interactive(
+    command: &str,
+    dir: &Path,
+    mounts: &[PathBuf],
+    backend: Backend,
+    recording: Option<&record::Recording>,
+) -> std::process::Command {
+    let (program, args, _) = invocation(command, dir, mounts, backend, true);
+
+    let mut command = record::command(program, args, recording);
+    command.current_dir(dir);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_escapes_single_quotes() {
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,149 @@
+//! Course-specific checks written as [Rhai](https://rhai.rs) scripts in the
+//! assignment's `checks/` directory, run with the other
+//! [`file_checks`](crate::file_checks).
+//!
+//! Each script gets `files`, an array of `#{ name, contents }`, and
+//! `student`, `#{ name, user_id, late, attempt }`, and returns whether it
+//! passed, or `#{ passed, message }`. Like the other file checks they inform
+//! the grade rather than score it.
+
+use crate::due_date::Lateness;
+use crate::file_checks::{FileCheck, FileFinding, Item};
+use crate::{DownloadedSubmission, File};
+use colored::Colorize;
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use std::path::Path;
+
+/// Enough for any reasonable check, so a runaway loop doesn't hang grading.
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+pub struct Script {
+    name: String,
+    ast: AST,
+}
+
+/// The scripts in `dir`, warning about any that don't compile.
+pub fn load(dir: &Path) -> Vec<Script> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "rhai"))
+        .collect();
+    paths.sort();
+
+    let engine = engine();
+
+    paths
+        .into_iter()
+        .filter_map(|path| match engine.compile_file(path.clone()) {
+            Ok(ast) => Some(Script {
+                name: path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                ast,
+            }),
+            Err(e) => {
+                println!("{} {}: {}", "warning:".yellow(), path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine
+}
+
+/// The assignment's scripts, bound to the submission they check.
+pub struct ScriptChecks<'a> {
+    scripts: &'a [Script],
+    student: Map,
+}
+
+impl<'a> ScriptChecks<'a> {
+    pub fn new(scripts: &'a [Script], submission: &DownloadedSubmission) -> Self {
+        let mut student = Map::new();
+        student.insert("name".into(), submission.name.clone().into());
+        student.insert("user_id".into(), (submission.user_id as i64).into());
+        student.insert(
+            "late".into(),
+            matches!(submission.lateness, Lateness::Late(_) | Lateness::Marked).into(),
+        );
+        student.insert(
+            "attempt".into(),
+            submission
+                .attempt
+                .map_or(Dynamic::UNIT, |a| (a as i64).into()),
+        );
+
+        Self { scripts, student }
+    }
+
+    fn eval(&self, script: &Script, files: &[File]) -> Result<Dynamic, Box<rhai::EvalAltResult>> {
+        let files: Array = files
+            .iter()
+            .map(|f| {
+                let mut file = Map::new();
+                file.insert("name".into(), f.name.clone().into());
+                file.insert(
+                    "contents".into(),
+                    f.contents.clone().unwrap_or_default().into(),
+                );
+                file.into()
+            })
+            .collect();
+
+        let mut scope = Scope::new();
+        scope.push("files", files);
+        scope.push("student", self.student.clone());
+
+        engine().eval_ast_with_scope(&mut scope, &script.ast)
+    }
+
+    fn item(&self, script: &Script, files: &[File]) -> Item {
+        let name = &script.name;
+
+        let (passed, message) = match self.eval(script, files) {
+            Ok(result) if result.is_bool() => (result.as_bool().unwrap_or(false), None),
+            Ok(result) => match result.try_cast::<Map>() {
+                Some(map) => (
+                    map.get("passed")
+                        .and_then(|p| p.as_bool().ok())
+                        .unwrap_or(false),
+                    map.get("message").map(|m| m.to_string()),
+                ),
+                None => (false, Some("didn't return a bool or a map".into())),
+            },
+            Err(e) => (false, Some(format!("error: {e}"))),
+        };
+
+        let label = message.map_or_else(|| name.clone(), |m| format!("{name}: {m}"));
+
+        Item {
+            name: label.clone(),
+            description: label,
+            passed,
+        }
+    }
+}
+
+impl FileCheck for ScriptChecks<'_> {
+    fn run(&self, files: &[File]) -> FileFinding {
+        FileFinding {
+            title: "Script checks",
+            items: self
+                .scripts
+                .iter()
+                .map(|script| self.item(script, files))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Keeps canvas access tokens in the system keyring (Secret Service, Keychain
+//! or Credential Manager) instead of a plaintext `.env`, one per canvas
+//! instance.
+
+use dialoguer::{theme::ColorfulTheme, Password};
+use keyring::Entry;
+
+const SERVICE: &str = "grader";
+
+/// OAuth tokens are kept apart from access tokens saved with `save-token`.
+const OAUTH_SERVICE: &str = "grader-oauth";
+
+/// The token saved for the canvas instance, if any.
+pub fn token(base_url: &str) -> Option<String> {
+    Entry::new(SERVICE, base_url).ok()?.get_password().ok()
+}
+
+/// The OAuth tokens saved for the canvas instance, as json.
+pub fn oauth_tokens(base_url: &str) -> Option<String> {
+    Entry::new(OAUTH_SERVICE, base_url)
+        .ok()?
+        .get_password()
+        .ok()
+}
+
+pub fn save_oauth_tokens(base_url: &str, json: &str) -> Result<(), keyring::Error> {
+    Entry::new(OAUTH_SERVICE, base_url)?.set_password(json)
+}
+
+/// Asks for a token and saves it for the canvas instance.
+pub fn save_token(base_url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let token = Password::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Access token for {base_url}"))
+        .interact()?;
+
+    Entry::new(SERVICE, base_url)?.set_password(&token)?;
+
+    println!("Saved, CANVAS_ACCESS_TOKEN can be removed from .env");
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Near-identical submissions, for screening without MOSS. Each
+//! submission's files of interest are fingerprinted by winnowing (the
+//! algorithm behind MOSS): comments, string literals and whitespace are
+//! dropped, every run of [`K`] characters hashed and the smallest hash of
+//! each [`WINDOW`] consecutive ones kept. Pairs sharing at least
+//! `similarity` (default 0.5) of the smaller submission's fingerprints are
+//! flagged. Fingerprints from `starter/` are ignored.
+
+use crate::{banned, config, file_checks, DownloadedSubmission, TEXT_ENTRY};
+use colored::Colorize;
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Characters per hashed run, short enough to catch copied statements but
+/// long enough that common idioms don't match.
+const K: usize = 25;
+
+/// Hashes per window, one of which is kept.
+const WINDOW: usize = 4;
+
+const THRESHOLD: f64 = 0.5;
+
+/// Submissions with fewer fingerprints are too small to compare.
+const MIN_FINGERPRINTS: usize = 20;
+
+/// Another student whose submission is similar.
+#[derive(Debug)]
+pub struct Similar {
+    pub name: String,
+    /// fraction of the smaller submission's fingerprints shared
+    pub score: f64,
+}
+
+fn fingerprints(text: &str) -> HashSet<u64> {
+    let bytes = text.as_bytes();
+
+    let hashes: Vec<u64> = bytes
+        .windows(K)
+        .map(|gram| {
+            let mut hasher = DefaultHasher::new();
+            gram.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect();
+
+    if hashes.len() < WINDOW {
+        return hashes.into_iter().collect();
+    }
+
+    hashes
+        .windows(WINDOW)
+        .filter_map(|w| w.iter().min().copied())
+        .collect()
+}
+
+/// Fingerprints of the files of interest below `dir`.
+fn fingerprint_dir(dir: &Path, files: &Regex) -> HashSet<u64> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_lowercase();
+            files.is_match(&name) && !name.contains("readme") && name != TEXT_ENTRY
+        })
+        .filter_map(|e| {
+            let contents = std::fs::read_to_string(e.path()).ok()?;
+            let name = e.file_name().to_string_lossy().to_lowercase();
+
+            let code = match name.ends_with(".c") || name.ends_with(".h") {
+                true => banned::strip(&contents),
+                false => contents,
+            };
+
+            let text: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+            Some(fingerprints(&text))
+        })
+        .flatten()
+        .collect()
+}
+
+/// The submissions similar to each student's, most similar first.
+pub fn find(
+    submissions: &[DownloadedSubmission],
+    starter: Option<&Path>,
+    settings: &config::Settings,
+) -> HashMap<usize, Vec<Similar>> {
+    let files = file_checks::files_of_interest(settings);
+    let threshold = settings.similarity.unwrap_or(THRESHOLD);
+
+    let starter = starter
+        .filter(|d| d.is_dir())
+        .map(|d| fingerprint_dir(d, &files))
+        .unwrap_or_default();
+
+    let prints: Vec<_> = submissions
+        .iter()
+        .map(|s| {
+            let mut prints = fingerprint_dir(&s.path, &files);
+            prints.retain(|p| !starter.contains(p));
+            (s, prints)
+        })
+        .filter(|(_, prints)| prints.len() >= MIN_FINGERPRINTS)
+        .collect();
+
+    let mut similar: HashMap<usize, Vec<Similar>> = HashMap::new();
+
+    for (i, (a, a_prints)) in prints.iter().enumerate() {
+        for (b, b_prints) in &prints[i + 1..] {
+            let shared = a_prints.intersection(b_prints).count();
+            let score = shared as f64 / a_prints.len().min(b_prints.len()) as f64;
+
+            if score < threshold {
+                continue;
+            }
+
+            similar.entry(a.user_id).or_default().push(Similar {
+                name: b.name.clone(),
+                score,
+            });
+            similar.entry(b.user_id).or_default().push(Similar {
+                name: a.name.clone(),
+                score,
+            });
+        }
+    }
+
+    for others in similar.values_mut() {
+        others.sort_by(|x, y| y.score.total_cmp(&x.score));
+    }
+
+    similar
+}
+
+pub fn print(similar: &[Similar]) {
+    for s in similar {
+        println!(
+            "\t{} {:.0}% similar to {}",
+            "✗".red(),
+            s.score * 100.0,
+            s.name
+        );
+    }
+}
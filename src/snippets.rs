@@ -0,0 +1,109 @@
+//! Canned comments picked by fuzzy search while writing a submission comment.
+//!
+//! The assignment's `snippets.txt` has one snippet per line, optionally
+//! starting with its points, e.g. `-2 missing error check on malloc in
+//! {file}: {points}`. `{points}` is filled in with the snippet's points and
+//! `{file}` with one of the student's files, asked for when there are several.
+
+use crate::template;
+use dialoguer::{theme::ColorfulTheme, FuzzySelect};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+use walkdir::WalkDir;
+
+static LINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:([+-]?\d+(?:\.\d+)?)\s+)?(.+)$").unwrap());
+
+#[derive(Debug)]
+pub struct Snippet {
+    pub points: Option<f64>,
+    pub text: String,
+}
+
+pub fn parse(contents: &str) -> Vec<Snippet> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| LINE.captures(l))
+        .map(|caps| Snippet {
+            points: caps.get(1).and_then(|p| p.as_str().parse().ok()),
+            text: caps[2].trim().to_string(),
+        })
+        .collect()
+}
+
+/// The student's files below `dir`, relative to it.
+fn files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<_> = WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            e.path()
+                .strip_prefix(dir)
+                .ok()
+                .map(|p| p.display().to_string())
+        })
+        .collect();
+
+    files.sort();
+    files
+}
+
+/// Picks snippets until done, one line of the comment each.
+pub fn compose(snippets: &[Snippet], dir: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let theme = ColorfulTheme::default();
+    let files = files(dir);
+
+    let items: Vec<_> = std::iter::once("Done".to_string())
+        .chain(snippets.iter().map(|s| match s.points {
+            Some(points) => format!("{points:+} {}", s.text),
+            None => s.text.clone(),
+        }))
+        .collect();
+
+    let mut lines = vec![];
+
+    loop {
+        let selection = FuzzySelect::with_theme(&theme)
+            .with_prompt("Snippet")
+            .items(&items)
+            .default(0)
+            .interact()?;
+
+        let Some(snippet) = selection.checked_sub(1).map(|i| &snippets[i]) else {
+            break;
+        };
+
+        let mut vars = HashMap::new();
+
+        if let Some(points) = snippet.points {
+            vars.insert("points", format!("{points:+}"));
+        }
+
+        if snippet.text.contains("{file}") {
+            let file = match files.len() {
+                0 => None,
+                1 => Some(files[0].clone()),
+                _ => Some(
+                    files[FuzzySelect::with_theme(&theme)
+                        .with_prompt("File")
+                        .items(&files)
+                        .interact()?]
+                    .clone(),
+                ),
+            };
+
+            vars.extend(file.map(|f| ("file", f)));
+        }
+
+        let line = template::expand(&snippet.text, &vars);
+        println!("\t{line}");
+        lines.push(line);
+    }
+
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,203 @@
+//! Spell checking (through `aspell -a` or anything else speaking the ispell
+//! pipe protocol) and an optional LanguageTool grammar pass for comments.
+
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use serde::Deserialize;
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[derive(Debug)]
+pub struct Issue {
+    line: usize,
+    text: String,
+    message: Option<String>,
+    suggestions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct LanguageToolResponse {
+    matches: Vec<LanguageToolMatch>,
+}
+
+#[derive(Deserialize)]
+struct LanguageToolMatch {
+    message: String,
+    offset: usize,
+    length: usize,
+    replacements: Vec<LanguageToolReplacement>,
+}
+
+#[derive(Deserialize)]
+struct LanguageToolReplacement {
+    value: String,
+}
+
+fn spelling(text: &str) -> Result<Vec<Issue>, Box<dyn std::error::Error>> {
+    let command = env::var("GRADER_SPELL_CMD").unwrap_or_else(|_| "aspell -a".into());
+
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            println!("{} could not run {}: {}", "warning:".yellow(), command, e);
+            return Ok(vec![]);
+        }
+    };
+
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        for line in text.lines() {
+            // ^ keeps the checker from treating the line as a command
+            writeln!(stdin, "^{line}")?;
+        }
+    }
+
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        println!(
+            "{} {} failed, skipping spell check",
+            "warning:".yellow(),
+            command
+        );
+        return Ok(vec![]);
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+
+    let mut issues = vec![];
+    let mut line = 0;
+
+    // the first line is the version banner, every input line ends with a
+    // blank line
+    for result in output.lines().skip(1) {
+        if result.is_empty() {
+            line += 1;
+            continue;
+        }
+
+        let mut parts = result.splitn(2, ':');
+        let head = parts.next().unwrap_or_default();
+        let suggestions = parts
+            .next()
+            .map(|s| s.split(',').map(|s| s.trim().to_string()).collect())
+            .unwrap_or_default();
+
+        let mut head = head.split_whitespace();
+        if let (Some("&" | "#"), Some(word)) = (head.next(), head.next()) {
+            issues.push(Issue {
+                line,
+                text: word.to_string(),
+                message: None,
+                suggestions,
+            });
+        }
+    }
+
+    Ok(issues)
+}
+
+async fn grammar(url: &str, text: &str) -> Result<Vec<Issue>, Box<dyn std::error::Error>> {
+    let language = env::var("GRADER_LANGUAGETOOL_LANGUAGE").unwrap_or_else(|_| "en-US".into());
+
+    let response: LanguageToolResponse = reqwest::Client::new()
+        .post(url)
+        .form(&[("text", text), ("language", language.as_str())])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let chars: Vec<char> = text.chars().collect();
+
+    Ok(response
+        .matches
+        .into_iter()
+        .map(|m| {
+            let start = m.offset.min(chars.len());
+            let end = (m.offset + m.length).min(chars.len());
+
+            Issue {
+                line: chars[..start].iter().filter(|&&c| c == '\n').count(),
+                text: chars[start..end].iter().collect(),
+                message: Some(m.message),
+                suggestions: m
+                    .replacements
+                    .into_iter()
+                    .take(5)
+                    .map(|r| r.value)
+                    .collect(),
+            }
+        })
+        .collect())
+}
+
+pub async fn check(text: &str) -> Result<Vec<Issue>, Box<dyn std::error::Error>> {
+    let mut issues = spelling(text)?;
+
+    if let Ok(url) = env::var("GRADER_LANGUAGETOOL_URL") {
+        issues.extend(grammar(&url, text).await?);
+    }
+
+    issues.sort_by_key(|i| i.line);
+
+    Ok(issues)
+}
+
+/// Prints any spelling or grammar issues inline and asks whether the text is
+/// good to go anyway. Returns `true` when the text can be used as is.
+pub async fn review(text: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let issues = check(text).await?;
+
+    if issues.is_empty() {
+        return Ok(true);
+    }
+
+    let lines: Vec<_> = text.lines().collect();
+
+    for issue in &issues {
+        let line = lines.get(issue.line).copied().unwrap_or_default();
+
+        println!(
+            "{} {}",
+            format!("{:>4}:", issue.line + 1).dimmed(),
+            line.replacen(&issue.text, &issue.text.red().underline().to_string(), 1)
+        );
+
+        if let Some(message) = &issue.message {
+            println!("      {}", message.yellow());
+        }
+
+        if !issue.suggestions.is_empty() {
+            println!(
+                "      {} → {}",
+                issue.text,
+                issue
+                    .suggestions
+                    .iter()
+                    .take(5)
+                    .map(|s| s.green().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    Ok(Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "{} possible issues, use the comment anyway?",
+            issues.len()
+        ))
+        .default(false)
+        .interact()?)
+}
@@ -0,0 +1,118 @@
+//! Portions from a staff csv (`--staff <csv>`), so the whole staff splits the
+//! students the same way. Each row has a `TA` and optionally a `Student`,
+//! given as a canvas id, login id or name. Students no row assigns are dealt
+//! out round-robin, alphabetically, among every TA in the file, so listing
+//! the TAs alone splits the whole course.
+
+use crate::{canvas, roster::normalize};
+use dialoguer::{theme::ColorfulTheme, FuzzySelect};
+use std::collections::HashSet;
+use std::path::Path;
+
+const TA: &[&str] = &["ta", "grader", "staff"];
+const STUDENT: &[&str] = &["student", "id", "login id", "sis login id", "name"];
+
+pub struct Staff {
+    /// in the order they first appear
+    pub tas: Vec<String>,
+    /// (TA, student)
+    assigned: Vec<(usize, String)>,
+}
+
+impl Staff {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut reader = csv::Reader::from_path(path)?;
+
+        let headers: Vec<_> = reader
+            .headers()?
+            .iter()
+            .map(|h| h.trim().to_lowercase())
+            .collect();
+        let column = |aliases: &[&str]| headers.iter().position(|h| aliases.contains(&h.as_str()));
+
+        let ta = column(TA).ok_or("the staff file has no TA column")?;
+        let student = column(STUDENT);
+
+        let mut staff = Self {
+            tas: vec![],
+            assigned: vec![],
+        };
+
+        for record in reader.records() {
+            let record = record?;
+            let field = |i: usize| record.get(i).map(str::trim).filter(|f| !f.is_empty());
+
+            let Some(name) = field(ta) else {
+                continue;
+            };
+
+            let index = match staff.tas.iter().position(|t| t == name) {
+                Some(index) => index,
+                None => {
+                    staff.tas.push(name.to_string());
+                    staff.tas.len() - 1
+                }
+            };
+
+            if let Some(student) = student.and_then(field) {
+                staff.assigned.push((index, student.to_string()));
+            }
+        }
+
+        match staff.tas.is_empty() {
+            true => Err("the staff file lists no TAs".into()),
+            false => Ok(staff),
+        }
+    }
+
+    /// Asks which TA is grading.
+    pub fn pick(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        Ok(FuzzySelect::with_theme(&ColorfulTheme::default())
+            .with_prompt("Grader")
+            .items(&self.tas)
+            .interact()?)
+    }
+
+    fn matches(student: &canvas::User, given: &str) -> bool {
+        given.parse::<usize>().ok() == Some(student.id)
+            || student
+                .login_id
+                .as_deref()
+                .is_some_and(|l| l.eq_ignore_ascii_case(given))
+            || [&student.sortable_name, &student.name]
+                .into_iter()
+                .any(|n| normalize(n) == normalize(given))
+    }
+
+    /// The user ids of the TA's students among the course's.
+    pub fn students(&self, ta: usize, students: &[canvas::User]) -> HashSet<usize> {
+        let mut mine = HashSet::new();
+        let mut unassigned = vec![];
+
+        for student in students {
+            match self
+                .assigned
+                .iter()
+                .find(|(_, given)| Self::matches(student, given))
+            {
+                Some((owner, _)) if *owner == ta => {
+                    mine.insert(student.id);
+                }
+                Some(_) => {}
+                None => unassigned.push(student),
+            }
+        }
+
+        unassigned.sort_by(|a, b| a.sortable_name.cmp(&b.sortable_name));
+
+        mine.extend(
+            unassigned
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| i % self.tas.len() == ta)
+                .map(|(_, s)| s.id),
+        );
+
+        mine
+    }
+}
@@ -0,0 +1,145 @@
+//! Style checks of the student's C files with clang-format and clang-tidy,
+//! enabled with `style = ["clang-format", "clang-tidy"]` in `grader.toml`.
+//! Each file passes when the tool reports nothing. The assignment's
+//! `.clang-format` and `.clang-tidy` are used over any the student submitted.
+
+use crate::config;
+use crate::file_checks::{FileCheck, FileFinding, Item};
+use crate::File;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// `file.c:3:5: warning: ...`, not counting notes.
+static DIAGNOSTIC: Lazy<Regex> = Lazy::new(|| Regex::new(r":\d+:\d+: (warning|error):").unwrap());
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Tool {
+    ClangFormat,
+    ClangTidy,
+}
+
+impl Tool {
+    fn name(self) -> &'static str {
+        match self {
+            Tool::ClangFormat => "clang-format",
+            Tool::ClangTidy => "clang-tidy",
+        }
+    }
+
+    fn command(self, file: &Path, assignment_dir: Option<&Path>) -> Command {
+        let mut command = Command::new(self.name());
+
+        match self {
+            Tool::ClangFormat => {
+                command.arg("--dry-run");
+
+                if let Some(style) = config_file(assignment_dir, config::CLANG_FORMAT) {
+                    command.arg(format!("--style=file:{}", style.display()));
+                }
+
+                command.arg(file);
+            }
+            Tool::ClangTidy => {
+                command.arg("--quiet");
+
+                if let Some(config) = config_file(assignment_dir, config::CLANG_TIDY) {
+                    command.arg(format!("--config-file={}", config.display()));
+                }
+
+                // no compilation database, headers are found next to the file
+                command.arg(file).arg("--");
+            }
+        }
+
+        command
+    }
+
+    fn violations(self) -> &'static str {
+        match self {
+            Tool::ClangFormat => "style violations",
+            Tool::ClangTidy => "diagnostics",
+        }
+    }
+}
+
+fn config_file(assignment_dir: Option<&Path>, name: &str) -> Option<PathBuf> {
+    assignment_dir
+        .map(|d| d.join(name))
+        .filter(|p| p.is_file())
+        .and_then(|p| std::fs::canonicalize(p).ok())
+}
+
+pub struct StyleCheck {
+    pub tools: Vec<Tool>,
+    pub assignment_dir: Option<PathBuf>,
+    pub dir: PathBuf,
+}
+
+impl StyleCheck {
+    fn item(&self, tool: Tool, file: &File) -> Item {
+        let output = tool
+            .command(&file.path, self.assignment_dir.as_deref())
+            .current_dir(&self.dir)
+            .output();
+
+        let (name, passed) = match output {
+            Ok(output) => {
+                let count = String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .chain(String::from_utf8_lossy(&output.stderr).lines())
+                    .filter(|l| DIAGNOSTIC.is_match(l))
+                    .count();
+
+                (
+                    format!(
+                        "{}: {} {} {}",
+                        file.name,
+                        count,
+                        tool.name(),
+                        tool.violations()
+                    ),
+                    count == 0,
+                )
+            }
+            Err(_) => (
+                format!(
+                    "{}: couldn't run {}, is it installed?",
+                    file.name,
+                    tool.name()
+                ),
+                false,
+            ),
+        };
+
+        Item {
+            name,
+            description: format!("{} has no {} {}", file.name, tool.name(), tool.violations()),
+            passed,
+        }
+    }
+}
+
+impl FileCheck for StyleCheck {
+    fn run(&self, files: &[File]) -> FileFinding {
+        let sources: Vec<_> = files
+            .iter()
+            .filter(|f| {
+                let name = f.name.to_lowercase();
+                name.ends_with(".c") || name.ends_with(".h")
+            })
+            .collect();
+
+        FileFinding {
+            title: "Style",
+            items: self
+                .tools
+                .iter()
+                .flat_map(|&tool| sources.iter().map(move |f| self.item(tool, f)))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! `{placeholder}` substitution for comment templates.
+//!
+//! Unknown placeholders, or ones without a value yet, are left as is so they
+//! stand out when editing the comment.
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{(\w+)\}").unwrap());
+
+pub fn expand(template: &str, vars: &HashMap<&str, String>) -> String {
+    PLACEHOLDER
+        .replace_all(template, |caps: &Captures| {
+            vars.get(&caps[1])
+                .cloned()
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_fills_in_known_placeholders() {
+        let vars = HashMap::from([("name", "Ada".to_string()), ("score", "9".to_string())]);
+
+        assert_eq!(
+            expand(
+                "Hi {name}, {score}/{points_possible} {not a placeholder}",
+                &vars
+            ),
+            "Hi Ada, 9/{points_possible} {not a placeholder}"
+        );
+    }
+}
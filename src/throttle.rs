@@ -0,0 +1,47 @@
+//! Slows requests down as canvas's rate limit runs low, before it starts
+//! rejecting them. Canvas reports what is left of the limit in
+//! `X-Rate-Limit-Remaining` and what each request cost in `X-Request-Cost`.
+
+use once_cell::sync::Lazy;
+use reqwest::header::HeaderMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Canvas starts each client at 700, requests slow down below this.
+const LOW: f64 = 300.0;
+
+/// Delay when the limit is used up.
+const MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// What was left after the last request and what it cost.
+static LIMIT: Lazy<Mutex<Option<(f64, f64)>>> = Lazy::new(|| Mutex::new(None));
+
+fn header(headers: &HeaderMap, name: &str) -> Option<f64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+pub fn update(headers: &HeaderMap) {
+    if let Some(remaining) = header(headers, "x-rate-limit-remaining") {
+        let cost = header(headers, "x-request-cost").unwrap_or_default();
+        *LIMIT.lock().unwrap() = Some((remaining, cost));
+    }
+}
+
+/// Whether canvas rejected the request for going over the limit.
+pub fn exceeded(headers: &HeaderMap) -> bool {
+    header(headers, "x-rate-limit-remaining").is_some_and(|r| r <= 0.0)
+}
+
+/// Waits longer the closer the next request, if it costs as much as the
+/// last, would bring the limit to zero.
+pub async fn wait() {
+    let Some((remaining, cost)) = *LIMIT.lock().unwrap() else {
+        return;
+    };
+
+    let expected = (remaining - cost).max(0.0);
+
+    if expected < LOW {
+        tokio::time::sleep(MAX_DELAY.mul_f64(1.0 - expected / LOW)).await;
+    }
+}
@@ -0,0 +1,53 @@
+//! Re-runs the checks whenever a file in the submission changes, e.g. while
+//! walking a student through a fix.
+
+use crate::checks::{self, Check};
+use crate::sandbox;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+const POLL: Duration = Duration::from_secs(1);
+
+/// Modification time of every file below `dir`.
+fn snapshot(dir: &Path) -> HashMap<PathBuf, SystemTime> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| Some((e.path().to_path_buf(), e.metadata().ok()?.modified().ok()?)))
+        .collect()
+}
+
+/// Runs until ctrl-c, which only stops watching, so the caller can clean up.
+pub async fn run(
+    checks: &[Check],
+    dir: &Path,
+    backend: sandbox::Backend,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Watching {} for changes, ctrl-c to stop", dir.display());
+
+    let mut last = snapshot(dir);
+
+    // listening replaces the default handler, which would exit
+    let stop = tokio::signal::ctrl_c();
+    tokio::pin!(stop);
+
+    loop {
+        tokio::select! {
+            _ = &mut stop => return Ok(()),
+            _ = tokio::time::sleep(POLL) => {}
+        }
+
+        if snapshot(dir) == last {
+            continue;
+        }
+
+        checks::print(&checks::run_all(checks, dir, backend).await?);
+
+        // taken after the run so build artifacts do not trigger another one
+        last = snapshot(dir);
+    }
+}